@@ -0,0 +1,167 @@
+use tantivy::query::{BooleanQuery, EmptyQuery, EnableScoring, Explanation, Occur, Query, Scorer, Weight};
+use tantivy::{DocId, DocSet, Score, SegmentReader, TantivyError, Term};
+
+use crate::error::{Error, ErrorCode};
+
+/// The most combinations of `SHOULD` clauses that a boolean query with a
+/// minimum number of matching clauses can expand into.
+pub const MAX_SHOULD_MATCH_COMBINATIONS: usize = 1024;
+
+/// Builds a boolean query whose clauses can be explained.
+pub fn boolean_query(clauses: Vec<(Occur, Box<dyn Query>)>) -> Box<dyn Query> {
+    let clauses = clauses.into_iter().map(|(occur, query)| (occur, nestable(query))).collect();
+    Box::new(BooleanQuery::new(clauses))
+}
+
+/// Wraps a query so that it can be explained as a clause of a compound
+/// query, such as a boolean or disjunction max query.
+pub fn nestable(query: Box<dyn Query>) -> Box<dyn Query> {
+    if query.is::<Nestable>() {
+        return query;
+    }
+    Box::new(Nestable(query))
+}
+
+/// Builds a boolean query in which at least `minimum` of the `SHOULD` clauses
+/// must match. Tantivy has no such option, so the `SHOULD` clauses are
+/// replaced by a required disjunction of every conjunction of `minimum` of
+/// them. A document that matches more clauses matches more conjunctions, so
+/// it still scores higher.
+pub fn minimum_should_match_query(
+    clauses: Vec<(Occur, Box<dyn Query>)>,
+    minimum: usize,
+) -> Result<Box<dyn Query>, Error> {
+    let (should, mut clauses): (Vec<_>, Vec<_>) = clauses
+        .into_iter()
+        .partition(|(occur, _)| *occur == Occur::Should);
+    if minimum == 0 {
+        clauses.extend(should);
+        return Ok(boolean_query(clauses));
+    }
+    if minimum > should.len() {
+        return Ok(Box::new(EmptyQuery));
+    }
+    if count_combinations(should.len(), minimum) > MAX_SHOULD_MATCH_COMBINATIONS {
+        return Err(Error::range_error(ErrorCode::InvalidArgument, format!(
+            "requiring {minimum} of {} SHOULD clauses to match needs more than \
+             {MAX_SHOULD_MATCH_COMBINATIONS} combinations of clauses",
+            should.len(),
+        )));
+    }
+    let disjunction = combinations(should.len(), minimum)
+        .into_iter()
+        .map(|combination| {
+            let conjunction = match combination.as_slice() {
+                [i] => should[*i].1.box_clone(),
+                _ => boolean_query(combination.iter().map(|&i| (Occur::Must, should[i].1.box_clone())).collect()),
+            };
+            (Occur::Should, conjunction)
+        })
+        .collect();
+    clauses.push((Occur::Must, boolean_query(disjunction)));
+    Ok(boolean_query(clauses))
+}
+
+/// Counts the combinations of `k` of `n` items, saturating on overflow.
+fn count_combinations(n: usize, k: usize) -> usize {
+    (0..k.min(n - k)).fold(1, |count: usize, i| count.saturating_mul(n - i) / (i + 1))
+}
+
+/// Lists the combinations of `k` of the indices `0..n`, in lexicographic order.
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    let mut result = vec![];
+    let mut combination: Vec<usize> = (0..k).collect();
+    loop {
+        result.push(combination.clone());
+        // Advance the rightmost index that isn't already at its last position.
+        let Some(i) = (0..k).rev().find(|&i| combination[i] < n - k + i) else {
+            return result;
+        };
+        combination[i] += 1;
+        for j in i + 1..k {
+            combination[j] = combination[j - 1] + 1;
+        }
+    }
+}
+
+/// A query that can be explained as a clause of a compound query. Tantivy
+/// explains every positive clause of a compound query, even the clauses that
+/// don't match the document. Explaining a compound clause, such as a phrase
+/// or boolean query, seeks to the document from wherever its scorer starts,
+/// which trips a debug assertion in Tantivy if that's past the document, so
+/// this checks for a match first.
+#[derive(Debug)]
+struct Nestable(Box<dyn Query>);
+
+impl Clone for Nestable {
+    fn clone(&self) -> Self {
+        Nestable(self.0.box_clone())
+    }
+}
+
+impl Query for Nestable {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> tantivy::Result<Box<dyn Weight>> {
+        Ok(Box::new(NestableWeight(self.0.weight(enable_scoring)?)))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        self.0.query_terms(visitor);
+    }
+}
+
+struct NestableWeight(Box<dyn Weight>);
+
+impl Weight for NestableWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> tantivy::Result<Box<dyn Scorer>> {
+        self.0.scorer(reader, boost)
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> tantivy::Result<Explanation> {
+        let mut scorer = self.0.scorer(reader, 1.0)?;
+        if scorer.doc() > doc || scorer.seek(doc) != doc {
+            return Err(TantivyError::InvalidArgument(format!("Document #({doc}) does not match")));
+        }
+        self.0.explain(reader, doc)
+    }
+
+    fn count(&self, reader: &SegmentReader) -> tantivy::Result<u32> {
+        self.0.count(reader)
+    }
+
+    fn for_each(&self, reader: &SegmentReader, callback: &mut dyn FnMut(DocId, Score)) -> tantivy::Result<()> {
+        self.0.for_each(reader, callback)
+    }
+
+    fn for_each_no_score(&self, reader: &SegmentReader, callback: &mut dyn FnMut(&[DocId])) -> tantivy::Result<()> {
+        self.0.for_each_no_score(reader, callback)
+    }
+
+    fn for_each_pruning(
+        &self,
+        threshold: Score,
+        reader: &SegmentReader,
+        callback: &mut dyn FnMut(DocId, Score) -> Score,
+    ) -> tantivy::Result<()> {
+        self.0.for_each_pruning(threshold, reader, callback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combinations_are_listed_in_order() {
+        assert_eq!(combinations(3, 1), vec![vec![0], vec![1], vec![2]]);
+        assert_eq!(combinations(4, 2), vec![vec![0, 1], vec![0, 2], vec![0, 3], vec![1, 2], vec![1, 3], vec![2, 3]]);
+        assert_eq!(combinations(2, 2), vec![vec![0, 1]]);
+    }
+
+    #[test]
+    fn combinations_are_counted() {
+        assert_eq!(count_combinations(4, 2), 6);
+        assert_eq!(count_combinations(10, 3), combinations(10, 3).len());
+        assert_eq!(count_combinations(5, 5), 1);
+        assert!(count_combinations(200, 100) > MAX_SHOULD_MATCH_COMBINATIONS);
+    }
+}
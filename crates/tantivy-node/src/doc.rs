@@ -0,0 +1,263 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use neon::prelude::*;
+use neon::types::buffer::TypedArray;
+use neon::types::extract::{Json, TryFromJs, TryIntoJs};
+use neon::types::{JsBigInt, JsBuffer, JsDate};
+use serde::{Deserialize, Serialize};
+use tantivy::schema::{Field, FieldType, OwnedValue, Schema};
+use tantivy::time::format_description::well_known::Rfc3339;
+use tantivy::{DateTime, Document as _, TantivyDocument, Term};
+
+use crate::error::{Error, ErrorCode};
+use crate::num::{i54, u53, Project};
+
+/// Converts a JS object into a Tantivy document, interpreting each property
+/// according to the type of the schema field of the same name. Properties
+/// that don't name a field in the schema are ignored, as they are by
+/// `TantivyDocument::from_json_object`.
+pub fn from_js<'cx>(
+    cx: &mut Cx<'cx>,
+    schema: &Schema,
+    object: Handle<'cx, JsObject>,
+) -> NeonResult<TantivyDocument> {
+    let mut doc = TantivyDocument::default();
+    let keys = object.get_own_property_names(cx)?.to_vec(cx)?;
+    for key in keys {
+        let name = key.downcast_or_throw::<JsString, _>(cx)?.value(cx);
+        let Ok(field) = schema.get_field(&name) else {
+            continue;
+        };
+        let field_type = schema.get_field_entry(field).field_type();
+        let value: Handle<JsValue> = object.get(cx, key)?;
+        if value.is_a::<JsUndefined, _>(cx) {
+            continue;
+        }
+        match value.downcast::<JsArray, _>(cx) {
+            // Like JSON documents, an array supplies multiple values for the same field.
+            Ok(array) => {
+                for item in array.to_vec(cx)? {
+                    let item = value_from_js(cx, &name, field_type, item)?;
+                    doc.add_field_value(field, item);
+                }
+            }
+            Err(_) => {
+                let value = value_from_js(cx, &name, field_type, value)?;
+                doc.add_field_value(field, value);
+            }
+        }
+    }
+    Ok(doc)
+}
+
+/// Converts a single JS value into a value of the given field's type.
+pub fn value_from_js<'cx>(
+    cx: &mut Cx<'cx>,
+    name: &str,
+    field_type: &FieldType,
+    value: Handle<'cx, JsValue>,
+) -> NeonResult<OwnedValue> {
+    match field_type {
+        // Integers that don't fit in a JS number can only be represented
+        // precisely as BigInts, which have no JSON encoding.
+        FieldType::I64(_) => {
+            if let Ok(n) = value.downcast::<JsBigInt, _>(cx) {
+                return match n.to_i64(cx) {
+                    Ok(n) => Ok(OwnedValue::I64(n)),
+                    Err(err) => throw_range_error(cx, name, err),
+                };
+            }
+            if let Ok(n) = value.downcast::<JsNumber, _>(cx) {
+                let n: i54 = match n.value(cx).project() {
+                    Ok(n) => n,
+                    Err(err) => return throw_range_error(cx, name, err),
+                };
+                return Ok(OwnedValue::I64(n.into()));
+            }
+        }
+        FieldType::U64(_) => {
+            if let Ok(n) = value.downcast::<JsBigInt, _>(cx) {
+                return match n.to_u64(cx) {
+                    Ok(n) => Ok(OwnedValue::U64(n)),
+                    Err(err) => throw_range_error(cx, name, err),
+                };
+            }
+            if let Ok(n) = value.downcast::<JsNumber, _>(cx) {
+                let n: u53 = match n.value(cx).project() {
+                    Ok(n) => n,
+                    Err(err) => return throw_range_error(cx, name, err),
+                };
+                return Ok(OwnedValue::U64(n.into()));
+            }
+        }
+        // Dates can be supplied as JS `Date` objects or as epoch milliseconds,
+        // in addition to the RFC 3339 strings Tantivy parses from JSON.
+        FieldType::Date(_) => {
+            if let Ok(date) = value.downcast::<JsDate, _>(cx) {
+                let millis = date.value(cx);
+                return date_from_millis(name, millis).or_else(|err| err.throw(cx));
+            }
+            if let Ok(n) = value.downcast::<JsNumber, _>(cx) {
+                let millis = n.value(cx);
+                return date_from_millis(name, millis).or_else(|err| err.throw(cx));
+            }
+        }
+        // Binary values can be supplied as a `Buffer` or `Uint8Array`, in
+        // addition to the base64 strings Tantivy parses from JSON.
+        FieldType::Bytes(_) => {
+            if let Ok(bytes) = value.downcast::<JsTypedArray<u8>, _>(cx) {
+                return Ok(OwnedValue::Bytes(bytes.as_slice(cx).to_vec()));
+            }
+        }
+        _ => {}
+    }
+    let Json(json) = Json::<serde_json::Value>::from_js(cx, value)?;
+    value_from_json(name, field_type, json).or_else(|err| err.throw(cx))
+}
+
+fn throw_range_error<'cx, T>(cx: &mut Cx<'cx>, name: &str, err: impl Display) -> NeonResult<T> {
+    Error::range_error(ErrorCode::InvalidArgument, format!("field '{name}': {err}")).throw(cx)
+}
+
+/// Parses a JSON object into a Tantivy document, interpreting its values the
+/// same way as `from_js` where JSON can represent them.
+pub fn from_json(schema: &Schema, json: &str) -> Result<TantivyDocument, Error> {
+    let object: serde_json::Map<String, serde_json::Value> = serde_json::from_str(json).map_err(|err| {
+        Error::new(ErrorCode::InvalidArgument, format!("failed to parse document: {err}"))
+    })?;
+    let mut doc = TantivyDocument::default();
+    for (name, value) in object {
+        let Ok(field) = schema.get_field(&name) else {
+            continue;
+        };
+        let field_type = schema.get_field_entry(field).field_type();
+        match value {
+            serde_json::Value::Array(items) => {
+                for item in items {
+                    doc.add_field_value(field, value_from_json(&name, field_type, item)?);
+                }
+            }
+            value => doc.add_field_value(field, value_from_json(&name, field_type, value)?),
+        }
+    }
+    Ok(doc)
+}
+
+/// Converts a single JSON value into a value of the given field's type. Like
+/// JS numbers, JSON numbers are accepted as epoch milliseconds for dates,
+/// and as integers if they're safe integers. Integers written without a
+/// fraction or exponent are exact in JSON, so they're accepted in the full
+/// range of the field's type, as BigInts are.
+fn value_from_json(name: &str, field_type: &FieldType, json: serde_json::Value) -> Result<OwnedValue, Error> {
+    if let serde_json::Value::Number(n) = &json {
+        let float = n.as_f64().unwrap_or(f64::NAN);
+        match field_type {
+            FieldType::Date(_) => return date_from_millis(name, float),
+            FieldType::I64(_) if !n.is_i64() => {
+                let n: i54 = float.project().map_err(|err| range_error(name, err))?;
+                return Ok(OwnedValue::I64(n.into()));
+            }
+            FieldType::U64(_) if !n.is_u64() => {
+                let n: u53 = float.project().map_err(|err| range_error(name, err))?;
+                return Ok(OwnedValue::U64(n.into()));
+            }
+            _ => {}
+        }
+    }
+    field_type.value_from_json(json).map_err(|err| {
+        Error::new(ErrorCode::SchemaMismatch, format!("failed to parse document: field '{name}': {err}"))
+    })
+}
+
+fn range_error(name: &str, err: impl Display) -> Error {
+    Error::range_error(ErrorCode::InvalidArgument, format!("field '{name}': {err}"))
+}
+
+/// Builds the term that a field indexes for a single value, or `None` for
+/// values such as JSON objects that aren't indexed as a single term.
+pub fn term_from_value(field: Field, value: &OwnedValue) -> Option<Term> {
+    match value {
+        OwnedValue::Str(text) => Some(Term::from_field_text(field, text)),
+        OwnedValue::U64(n) => Some(Term::from_field_u64(field, *n)),
+        OwnedValue::I64(n) => Some(Term::from_field_i64(field, *n)),
+        OwnedValue::F64(n) => Some(Term::from_field_f64(field, *n)),
+        OwnedValue::Bool(b) => Some(Term::from_field_bool(field, *b)),
+        OwnedValue::Date(date) => Some(Term::from_field_date(field, *date)),
+        OwnedValue::Facet(facet) => Some(Term::from_facet(field, facet)),
+        OwnedValue::Bytes(bytes) => Some(Term::from_field_bytes(field, bytes)),
+        OwnedValue::IpAddr(addr) => Some(Term::from_field_ip_addr(field, *addr)),
+        _ => None,
+    }
+}
+
+fn date_from_millis(name: &str, millis: f64) -> Result<OwnedValue, Error> {
+    if millis.is_nan() {
+        return Err(range_error(name, "invalid date"));
+    }
+    let millis: i54 = millis.project().map_err(|err| range_error(name, err))?;
+    Ok(OwnedValue::Date(DateTime::from_timestamp_millis(millis.into())))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DateFormat {
+    #[default]
+    Iso,
+    Date,
+}
+
+/// A stored document, resolved against its schema on whichever thread
+/// retrieved it so that it can be converted into a JS object later.
+pub struct Document {
+    fields: BTreeMap<String, Vec<OwnedValue>>,
+    dates: DateFormat,
+}
+
+impl Document {
+    pub fn new(doc: &TantivyDocument, schema: &Schema, dates: DateFormat) -> Self {
+        Self {
+            fields: doc.to_named_doc(schema).0,
+            dates,
+        }
+    }
+
+    pub fn into_js<'cx>(self, cx: &mut Cx<'cx>) -> JsResult<'cx, JsObject> {
+        // Since a document may contain duplicate fields, each field is
+        // represented as an array, just as in `TantivyDocument::to_json`.
+        let object = cx.empty_object();
+        for (name, values) in self.fields {
+            let array = JsArray::new(cx, values.len());
+            for (i, value) in values.into_iter().enumerate() {
+                let value = value_to_js(cx, value, self.dates)?;
+                array.set(cx, i as u32, value)?;
+            }
+            object.set(cx, name.as_str(), array)?;
+        }
+        Ok(object)
+    }
+}
+
+fn value_to_js<'cx>(cx: &mut Cx<'cx>, value: OwnedValue, dates: DateFormat) -> JsResult<'cx, JsValue> {
+    match value {
+        OwnedValue::I64(n) => Ok(JsBigInt::from_i64(cx, n).upcast()),
+        OwnedValue::U64(n) => Ok(JsBigInt::from_u64(cx, n).upcast()),
+        OwnedValue::Date(date) => match dates {
+            DateFormat::Iso => match date.into_utc().format(&Rfc3339) {
+                Ok(iso) => Ok(cx.string(iso).upcast()),
+                Err(err) => Error::range_error(ErrorCode::Internal, err.to_string()).throw(cx),
+            },
+            DateFormat::Date => match cx.date(date.into_timestamp_millis() as f64) {
+                Ok(date) => Ok(date.upcast()),
+                Err(err) => Error::range_error(ErrorCode::Internal, err.to_string()).throw(cx),
+            },
+        },
+        // Tantivy stores IPv4 addresses as IPv4-mapped IPv6 addresses.
+        OwnedValue::IpAddr(addr) => match addr.to_ipv4_mapped() {
+            Some(addr) => Ok(cx.string(addr.to_string()).upcast()),
+            None => Ok(cx.string(addr.to_string()).upcast()),
+        },
+        OwnedValue::Bytes(bytes) => Ok(JsBuffer::from_slice(cx, &bytes)?.upcast()),
+        value => Json(value).try_into_js(cx),
+    }
+}
@@ -0,0 +1,282 @@
+use std::fmt::{self, Display};
+use std::net::AddrParseError;
+use std::num::{ParseIntError, TryFromIntError};
+use std::sync::PoisonError;
+
+use neon::prelude::*;
+use neon::types::extract::{self, TryIntoJs};
+use tantivy::directory::error::{OpenDirectoryError, OpenReadError};
+use tantivy::query::QueryParserError;
+use tantivy::schema::FacetParseError;
+use tantivy::TantivyError;
+
+use crate::num::ProjectionError;
+
+/// A stable identifier for a class of failure, exposed to JS as the `code`
+/// property of thrown errors so that callers can handle them programmatically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// A schema, document or value doesn't match the schema of the index.
+    SchemaMismatch,
+    /// A field name doesn't exist in the schema.
+    FieldNotFound,
+    /// The index writer is held by another process or a pending commit.
+    LockBusy,
+    /// The index writer isn't available because the index is read-only.
+    ReadOnly,
+    /// The index has been closed.
+    IndexClosed,
+    /// No index exists at the path being opened.
+    IndexNotFound,
+    /// Reading or writing the index directory failed.
+    IoError,
+    /// A query or pattern couldn't be parsed.
+    QueryParse,
+    /// An argument is malformed or out of range.
+    InvalidArgument,
+    /// Anything else, including internal failures of Tantivy.
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::SchemaMismatch => "SCHEMA_MISMATCH",
+            ErrorCode::FieldNotFound => "FIELD_NOT_FOUND",
+            ErrorCode::LockBusy => "LOCK_BUSY",
+            ErrorCode::ReadOnly => "READ_ONLY",
+            ErrorCode::IndexClosed => "INDEX_CLOSED",
+            ErrorCode::IndexNotFound => "INDEX_NOT_FOUND",
+            ErrorCode::IoError => "IO_ERROR",
+            ErrorCode::QueryParse => "QUERY_PARSE",
+            ErrorCode::InvalidArgument => "INVALID_ARGUMENT",
+            ErrorCode::Internal => "INTERNAL",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ErrorKind {
+    Error,
+    TypeError,
+    RangeError,
+}
+
+/// An error that is thrown to JS as an `Error`, `TypeError` or `RangeError`
+/// with a `code` property.
+#[derive(Debug)]
+pub struct Error {
+    code: ErrorCode,
+    kind: ErrorKind,
+    message: String,
+}
+
+impl Error {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self { code, kind: ErrorKind::Error, message: message.into() }
+    }
+
+    pub fn type_error(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self { code, kind: ErrorKind::TypeError, message: message.into() }
+    }
+
+    pub fn range_error(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self { code, kind: ErrorKind::RangeError, message: message.into() }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+
+    /// Creates the JS error, with this error's code as its `code` property.
+    pub fn into_js<'cx>(self, cx: &mut Cx<'cx>) -> JsResult<'cx, JsError> {
+        let error = match self.kind {
+            ErrorKind::Error => cx.error(self.message)?,
+            ErrorKind::TypeError => cx.type_error(self.message)?,
+            ErrorKind::RangeError => cx.range_error(self.message)?,
+        };
+        let code = cx.string(self.code.as_str());
+        error.set(cx, "code", code)?;
+        Ok(error)
+    }
+
+    /// Throws this error from a function that returns a `NeonResult`.
+    pub fn throw<'cx, T>(self, cx: &mut Cx<'cx>) -> NeonResult<T> {
+        let error = self.into_js(cx)?;
+        cx.throw(error)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// An error that Neon throws when it's returned from an exported method,
+/// task or constructor. Neon's conversion traits are sealed, so an `Error`
+/// can't be returned directly; `coded` converts it into one of these.
+pub trait Throwable: for<'cx> TryIntoJs<'cx> + Send + 'static {}
+
+impl<T: for<'cx> TryIntoJs<'cx> + Send + 'static> Throwable for T {}
+
+/// Runs the body of an exported method or constructor, converting its error
+/// into one that's thrown with its code. The body is boxed so that the
+/// returned error type doesn't depend on the type of the closure, which
+/// would tie it to the lifetime of `&self`.
+pub fn coded<'a, T>(body: Box<dyn FnOnce() -> Result<T, Error> + 'a>) -> Result<T, impl Throwable> {
+    body().map_err(|err| extract::with(move |cx| err.into_js(cx)))
+}
+
+// Plain messages describe invalid arguments; other failures use an explicit code.
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::new(ErrorCode::InvalidArgument, message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::new(ErrorCode::InvalidArgument, message)
+    }
+}
+
+impl From<TantivyError> for Error {
+    fn from(err: TantivyError) -> Self {
+        let code = match &err {
+            TantivyError::FieldNotFound(_) => ErrorCode::FieldNotFound,
+            TantivyError::SchemaError(_) | TantivyError::IncompatibleIndex(_) => ErrorCode::SchemaMismatch,
+            TantivyError::LockFailure(..) => ErrorCode::LockBusy,
+            TantivyError::OpenDirectoryError(_)
+            | TantivyError::OpenReadError(_)
+            | TantivyError::OpenWriteError(_)
+            | TantivyError::IoError(_)
+            | TantivyError::DataCorruption(_)
+            | TantivyError::IndexAlreadyExists => ErrorCode::IoError,
+            TantivyError::InvalidArgument(_) => ErrorCode::InvalidArgument,
+            _ => ErrorCode::Internal,
+        };
+        Error::new(code, err.to_string())
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::new(ErrorCode::IoError, err.to_string())
+    }
+}
+
+impl From<OpenDirectoryError> for Error {
+    fn from(err: OpenDirectoryError) -> Self {
+        let code = match err {
+            OpenDirectoryError::DoesNotExist(_) => ErrorCode::IndexNotFound,
+            _ => ErrorCode::IoError,
+        };
+        Error::new(code, err.to_string())
+    }
+}
+
+impl From<OpenReadError> for Error {
+    fn from(err: OpenReadError) -> Self {
+        Error::new(ErrorCode::IoError, err.to_string())
+    }
+}
+
+impl From<ProjectionError> for Error {
+    fn from(err: ProjectionError) -> Self {
+        Error::range_error(ErrorCode::InvalidArgument, err.to_string())
+    }
+}
+
+impl From<TryFromIntError> for Error {
+    fn from(err: TryFromIntError) -> Self {
+        Error::range_error(ErrorCode::InvalidArgument, err.to_string())
+    }
+}
+
+impl From<FacetParseError> for Error {
+    fn from(err: FacetParseError) -> Self {
+        Error::new(ErrorCode::InvalidArgument, err.to_string())
+    }
+}
+
+impl From<QueryParserError> for Error {
+    fn from(err: QueryParserError) -> Self {
+        let code = match err {
+            QueryParserError::FieldDoesNotExist(_) => ErrorCode::FieldNotFound,
+            _ => ErrorCode::QueryParse,
+        };
+        Error::new(code, err.to_string())
+    }
+}
+
+impl From<AddrParseError> for Error {
+    fn from(err: AddrParseError) -> Self {
+        Error::new(ErrorCode::QueryParse, err.to_string())
+    }
+}
+
+impl From<ParseIntError> for Error {
+    fn from(err: ParseIntError) -> Self {
+        Error::new(ErrorCode::QueryParse, err.to_string())
+    }
+}
+
+impl<T> From<PoisonError<T>> for Error {
+    fn from(_: PoisonError<T>) -> Self {
+        Error::new(ErrorCode::Internal, "mutex poisoned")
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::new(ErrorCode::IoError, err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tantivy_errors_map_to_codes() {
+        let code = |err: TantivyError| Error::from(err).code();
+        assert_eq!(code(TantivyError::FieldNotFound("title".into())), ErrorCode::FieldNotFound);
+        assert_eq!(code(TantivyError::SchemaError("bad".into())), ErrorCode::SchemaMismatch);
+        assert_eq!(code(TantivyError::IndexAlreadyExists), ErrorCode::IoError);
+        assert_eq!(code(TantivyError::InvalidArgument("bad".into())), ErrorCode::InvalidArgument);
+        assert_eq!(code(TantivyError::Poisoned), ErrorCode::Internal);
+    }
+
+    #[test]
+    fn query_parser_errors_map_to_codes() {
+        let code = |err: QueryParserError| Error::from(err).code();
+        assert_eq!(code(QueryParserError::FieldDoesNotExist("title".into())), ErrorCode::FieldNotFound);
+        assert_eq!(code(QueryParserError::SyntaxError("title:(".into())), ErrorCode::QueryParse);
+    }
+
+    #[test]
+    fn missing_directories_are_not_found() {
+        let err = OpenDirectoryError::DoesNotExist("/no/such/index".into());
+        assert_eq!(Error::from(err).code(), ErrorCode::IndexNotFound);
+    }
+
+    #[test]
+    fn plain_messages_are_invalid_arguments() {
+        let err = Error::from("no such thing");
+        assert_eq!(err.code(), ErrorCode::InvalidArgument);
+        assert_eq!(err.to_string(), "no such thing");
+    }
+
+    #[test]
+    fn poisoned_locks_are_internal_errors() {
+        assert_eq!(Error::from(PoisonError::new(())).code(), ErrorCode::Internal);
+    }
+
+    #[test]
+    fn codes_have_stable_names() {
+        assert_eq!(ErrorCode::LockBusy.as_str(), "LOCK_BUSY");
+        assert_eq!(ErrorCode::QueryParse.as_str(), "QUERY_PARSE");
+    }
+}
@@ -1,29 +1,46 @@
 use std::cell::RefCell;
-use std::path::PathBuf;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor};
+use std::net::{IpAddr, Ipv6Addr};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
 use std::str::CharIndices;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::{self, JoinHandle};
 
 use neon::{prelude::*, types::JsBigInt};
-use neon::types::extract::{Error, Json};
+use neon::types::buffer::TypedArray;
+use neon::types::extract::{self, Json, TryFromJs, TryIntoJs};
 
 use num::{u53, Project};
 use ordermap::OrderMap;
 use serde::{Deserialize, Serialize};
-use tantivy::collector::TopDocs;
-use tantivy::query::{Explanation, FuzzyTermQuery, PhrasePrefixQuery, PhraseQuery, RegexQuery, TermQuery};
-use tantivy::schema::{NumericOptions, SchemaBuilder, TextFieldIndexing};
+use tantivy::collector::{FacetCollector, TopDocs};
+use tantivy::directory::{Directory, MmapDirectory, RamDirectory};
+use tantivy::indexer::UserOperation;
+use tantivy::merge_policy::{LogMergePolicy, MergePolicy, NoMergePolicy};
+use tantivy::query::{AllQuery, BooleanQuery, BoostQuery, ConstScoreQuery, DisjunctionMaxQuery, EmptyQuery, ExistsQuery, Explanation, FuzzyTermQuery, PhrasePrefixQuery, PhraseQuery, QueryParser, RangeQuery, RegexQuery, TermQuery, TermSetQuery};
+use tantivy::json_utils::{convert_to_fast_value_and_get_term, JsonTermWriter};
+use tantivy::schema::{BytesOptions, DateOptions, DateTimePrecision, Facet, FacetOptions, FieldType, IntoIpv6Addr, IpAddrOptions, JsonObjectOptions, NumericOptions, OwnedValue, SchemaBuilder, TextFieldIndexing, Type};
 use tantivy::tokenizer::{AlphaNumOnlyFilter, AsciiFoldingFilter, Language, LowerCaser, RemoveLongFilter, SimpleTokenizer, Stemmer, StopWordFilter, TextAnalyzerBuilder, TokenStream, Tokenizer};
-use tantivy::{Document, IndexReader, ReloadPolicy, Score, Term};
+use tantivy::{DocAddress, IndexReader, ReloadPolicy, Score, SegmentId, Term};
 use tantivy::{schema::{Field, TextOptions}, IndexSettings, IndexWriter, TantivyDocument};
 
+pub mod boolean;
+pub mod doc;
+pub mod error;
 pub mod num;
 
+use error::{coded, Error, ErrorCode, Throwable};
+
 use tantivy_fst::Regex;
 
 // Explicitly-qualified Tantivy types to distinguish from our JS wrapper types of the same names.
 mod t {
     pub use tantivy::schema::Schema;
-    pub use tantivy::query::Query;
+    pub use tantivy::query::{Occur, Query};
     pub use tantivy::{Index, Searcher};
     pub use tantivy::tokenizer::TextAnalyzer;
 }
@@ -32,32 +49,204 @@ mod t {
 #[serde(default, rename_all = "camelCase")]
 struct IndexOptions {
     heap_size: f64,
+    num_threads: Option<f64>,
+    heap_size_per_thread: Option<f64>,
     reload_on: ReloadOnPolicy,
+    mode: OpenMode,
+    in_memory: bool,
+    merge_policy: Option<MergePolicyOptions>,
 }
 
 impl Default for IndexOptions {
     fn default() -> Self {
         Self {
-            heap_size: 10_000_000.0,
+            // Tantivy requires at least 15MB per writer thread.
+            heap_size: 50_000_000.0,
+            num_threads: None,
+            heap_size_per_thread: None,
             reload_on: ReloadOnPolicy::CommitWithDelay,
+            mode: OpenMode::Create,
+            in_memory: false,
+            merge_policy: None,
+        }
+    }
+}
+
+/// The merge policy of an index writer. Parameters of the log merge policy
+/// that aren't specified keep Tantivy's defaults.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum MergePolicyOptions {
+    #[serde(rename_all = "camelCase")]
+    Log {
+        min_num_segments: Option<usize>,
+        max_docs_before_merge: Option<usize>,
+        min_layer_size: Option<u32>,
+        level_log_size: Option<f64>,
+        del_docs_ratio_before_merge: Option<f32>,
+    },
+    NoMerge,
+}
+
+impl MergePolicyOptions {
+    fn build(&self) -> Box<dyn MergePolicy> {
+        match self {
+            MergePolicyOptions::Log {
+                min_num_segments,
+                max_docs_before_merge,
+                min_layer_size,
+                level_log_size,
+                del_docs_ratio_before_merge,
+            } => {
+                let mut policy = LogMergePolicy::default();
+                if let Some(n) = min_num_segments {
+                    policy.set_min_num_segments(*n);
+                }
+                if let Some(n) = max_docs_before_merge {
+                    policy.set_max_docs_before_merge(*n);
+                }
+                if let Some(n) = min_layer_size {
+                    policy.set_min_layer_size(*n);
+                }
+                if let Some(n) = level_log_size {
+                    policy.set_level_log_size(*n);
+                }
+                if let Some(ratio) = del_docs_ratio_before_merge {
+                    policy.set_del_docs_ratio_before_merge(*ratio);
+                }
+                Box::new(policy)
+            }
+            MergePolicyOptions::NoMerge => Box::new(NoMergePolicy),
+        }
+    }
+}
+
+/// The settings of an index writer, which are kept so that the writer can be
+/// recreated after it's consumed by waiting for its merging threads.
+#[derive(Clone)]
+struct WriterOptions {
+    heap_size: usize,
+    num_threads: Option<usize>,
+    merge_policy: Option<MergePolicyOptions>,
+}
+
+// Tantivy's `MEMORY_BUDGET_NUM_BYTES_MIN` and `MEMORY_BUDGET_NUM_BYTES_MAX`,
+// which aren't public.
+const MIN_HEAP_SIZE_PER_THREAD: usize = 15_000_000;
+const MAX_HEAP_SIZE_PER_THREAD: usize = u32::MAX as usize - 1_000_000;
+
+impl WriterOptions {
+    fn new(options: &IndexOptions) -> Result<Self, Error> {
+        let num_threads = options.num_threads.map(to_usize).transpose()?;
+        let heap_size = match (options.heap_size_per_thread, num_threads) {
+            (Some(heap_size_per_thread), Some(num_threads)) => {
+                to_usize(heap_size_per_thread)?.saturating_mul(num_threads)
+            }
+            (Some(_), None) => return Err("'heapSizePerThread' requires 'numThreads'".into()),
+            (None, _) => to_usize(options.heap_size)?,
+        };
+        // Without an explicit thread count, Tantivy uses fewer threads to
+        // give each thread the minimum heap size if it can.
+        let heap_size_per_thread = match num_threads {
+            Some(0) => {
+                return Err(Error::range_error(ErrorCode::InvalidArgument, "'numThreads' must be at least 1"));
+            }
+            Some(num_threads) => heap_size / num_threads,
+            None => heap_size,
+        };
+        if heap_size_per_thread < MIN_HEAP_SIZE_PER_THREAD {
+            return Err(Error::range_error(ErrorCode::InvalidArgument, format!(
+                "heap size of {heap_size_per_thread} bytes per writer thread is below \
+                 the minimum of {MIN_HEAP_SIZE_PER_THREAD} bytes"
+            )));
+        }
+        if num_threads.is_some() && heap_size_per_thread >= MAX_HEAP_SIZE_PER_THREAD {
+            return Err(Error::range_error(ErrorCode::InvalidArgument, format!(
+                "heap size of {heap_size_per_thread} bytes per writer thread must be less \
+                 than {MAX_HEAP_SIZE_PER_THREAD} bytes"
+            )));
+        }
+        Ok(Self {
+            heap_size,
+            num_threads,
+            merge_policy: options.merge_policy.clone(),
+        })
+    }
+
+    fn create(&self, index: &t::Index) -> Result<IndexWriter, Error> {
+        let writer = match self.num_threads {
+            Some(num_threads) => index.writer_with_num_threads(num_threads, self.heap_size)?,
+            None => index.writer(self.heap_size)?,
+        };
+        if let Some(merge_policy) = &self.merge_policy {
+            writer.set_merge_policy(merge_policy.build());
         }
+        Ok(writer)
     }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-#[serde(default, rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+struct GarbageCollection {
+    deleted_files: Vec<PathBuf>,
+    failed_to_delete_files: Vec<PathBuf>,
+}
+
+/// How to treat an existing index in the index directory.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum OpenMode {
+    /// Create a new index, failing if one already exists.
+    Create,
+    /// Open an existing index, taking its schema from the index metadata.
+    Open,
+    /// Open an existing index if there is one, or else create a new one.
+    OpenOrCreate,
+    /// Open an existing index for searching only. No writer is created, so
+    /// the index directory isn't locked and another process may write to it.
+    ReadOnly,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
 struct SearchOptions {
     top: f64,
+    dates: doc::DateFormat,
+    /// The fields searched by terms of a query string without a field name,
+    /// as in `ParseQueryOptions`.
+    default_fields: Vec<String>,
 }
 
 impl Default for SearchOptions {
     fn default() -> Self {
         Self {
             top: 10.0,
+            dates: doc::DateFormat::default(),
+            default_fields: vec![],
         }
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct ParseQueryOptions {
+    /// The fields searched by terms without a field name, which default to
+    /// all indexed text fields.
+    default_fields: Vec<String>,
+    /// Whether terms are combined with `AND` rather than `OR`.
+    conjunction_by_default: bool,
+    field_boosts: OrderMap<String, f64>,
+    /// Fields whose terms are matched as fuzzy terms.
+    fuzzy: OrderMap<String, FuzzyTermQueryOptions>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct BooleanQueryOptions {
+    /// The number of `SHOULD` clauses that a document must match.
+    minimum_should_match: Option<f64>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(default, rename_all = "camelCase")]
 struct TextAnalyzerFilters {
@@ -84,6 +273,7 @@ impl Default for TextAnalyzerFilters {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, rename_all = "camelCase")]
 struct FuzzyTermQueryOptions {
     max_distance: u32,
     transposition_costs_one: bool,
@@ -173,8 +363,9 @@ impl TextAnalyzerFilters {
     }
 
     fn apply_stop_words<T: Tokenizer>(self, builder: TextAnalyzerBuilder<T>) -> t::TextAnalyzer {
-        match self.filter_stop_words {
-            Some(language) => builder.filter(StopWordFilter::new(language.into()).unwrap()).build(),
+        // The language is checked to have stop words in `TextAnalyzer::new`.
+        match self.filter_stop_words.and_then(|language| StopWordFilter::new(language.into())) {
+            Some(filter) => builder.filter(filter).build(),
             None => builder.build(),
         }
     }
@@ -201,6 +392,36 @@ impl From<IndexRecordOption> for tantivy::schema::IndexRecordOption {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum Occur {
+    Must,
+    Should,
+    MustNot,
+}
+
+impl From<Occur> for t::Occur {
+    fn from(value: Occur) -> Self {
+        match value {
+            Occur::Must => t::Occur::Must,
+            Occur::Should => t::Occur::Should,
+            Occur::MustNot => t::Occur::MustNot,
+        }
+    }
+}
+
+impl From<tantivy::schema::IndexRecordOption> for IndexRecordOption {
+    fn from(value: tantivy::schema::IndexRecordOption) -> Self {
+        match value {
+            tantivy::schema::IndexRecordOption::Basic => IndexRecordOption::Basic,
+            tantivy::schema::IndexRecordOption::WithFreqs => IndexRecordOption::WithFreqs,
+            tantivy::schema::IndexRecordOption::WithFreqsAndPositions => {
+                IndexRecordOption::WithFreqsAndPositions
+            }
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 enum LanguageName {
     Arabic,
@@ -253,76 +474,344 @@ impl From<LanguageName> for Language {
 enum FieldDescriptor {
     Text {
         flags: Option<Vec<TextOption>>,
+        indexed: Option<bool>,
         tokenizer: Option<String>,
         index: Option<IndexRecordOption>,
+        fieldnorms: Option<bool>,
+        #[serde(rename = "fastTokenizer")]
+        fast_tokenizer: Option<String>,
+    },
+    String {
+        flags: Option<Vec<TextOption>>,
+        #[serde(rename = "fastTokenizer")]
+        fast_tokenizer: Option<String>,
     },
-    String { flags: Option<Vec<TextOption>> },
     F64 { flags: Option<Vec<NumericOption>> },
-    // TODO: | F64FieldDescriptor
-    // TODO: | I64FieldDescriptor
-    // TODO: | U64FieldDescriptor
-    // TODO: | DateFieldDescriptor
-    // TODO: | BoolFieldDescriptor
-    // TODO: | IpAddrFieldDescriptor
+    I64 { flags: Option<Vec<NumericOption>> },
+    U64 { flags: Option<Vec<NumericOption>> },
+    Date {
+        flags: Option<Vec<NumericOption>>,
+        precision: Option<DatePrecision>,
+    },
+    Bool { flags: Option<Vec<NumericOption>> },
+    IpAddr { flags: Option<Vec<NumericOption>> },
+    Bytes { flags: Option<Vec<NumericOption>> },
+    Json {
+        flags: Option<Vec<JsonOption>>,
+        indexed: Option<bool>,
+        tokenizer: Option<String>,
+        index: Option<IndexRecordOption>,
+        #[serde(rename = "fastTokenizer")]
+        fast_tokenizer: Option<String>,
+        #[serde(rename = "expandDots")]
+        expand_dots: Option<bool>,
+    },
+    Facet { flags: Option<Vec<FacetOption>> },
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 enum TextOption {
     STORED,
+    FAST,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 enum NumericOption {
     STORED,
     INDEXED,
+    FAST,
 }
 
-fn add_field(builder: &mut SchemaBuilder, name: &str, options: &FieldDescriptor) {
-    match options {
-        FieldDescriptor::Text { flags, tokenizer, index } => {
-            let mut options = TextOptions::default() | tantivy::schema::TEXT;
-            if let Some(flags) = flags {
-                for flag in flags {
-                    options = match flag {
-                        TextOption::STORED => options | tantivy::schema::STORED,
-                    };
+#[derive(Serialize, Deserialize, Debug, Clone)]
+enum FacetOption {
+    STORED,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+enum JsonOption {
+    STORED,
+    FAST,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum DatePrecision {
+    Seconds,
+    // JS dates have millisecond precision, so default to keeping all of it.
+    #[default]
+    Milliseconds,
+    Microseconds,
+    Nanoseconds,
+}
+
+impl From<DatePrecision> for DateTimePrecision {
+    fn from(value: DatePrecision) -> Self {
+        match value {
+            DatePrecision::Seconds => DateTimePrecision::Seconds,
+            DatePrecision::Milliseconds => DateTimePrecision::Milliseconds,
+            DatePrecision::Microseconds => DateTimePrecision::Microseconds,
+            DatePrecision::Nanoseconds => DateTimePrecision::Nanoseconds,
+        }
+    }
+}
+
+impl From<DateTimePrecision> for DatePrecision {
+    fn from(value: DateTimePrecision) -> Self {
+        match value {
+            DateTimePrecision::Seconds => DatePrecision::Seconds,
+            DateTimePrecision::Milliseconds => DatePrecision::Milliseconds,
+            DateTimePrecision::Microseconds => DatePrecision::Microseconds,
+            DateTimePrecision::Nanoseconds => DatePrecision::Nanoseconds,
+        }
+    }
+}
+
+// Tantivy's option types for numeric-like fields all accept the same
+// flags, but don't share a trait we could be generic over.
+trait NumericFieldOptions: Default {
+    fn with_flag(self, flag: NumericOption) -> Self;
+}
+
+macro_rules! impl_numeric_field_options {
+    ($($t:ty),*) => {
+        $(
+            impl NumericFieldOptions for $t {
+                fn with_flag(self, flag: NumericOption) -> Self {
+                    match flag {
+                        NumericOption::STORED => self | tantivy::schema::STORED,
+                        NumericOption::INDEXED => self | tantivy::schema::INDEXED,
+                        NumericOption::FAST => self | tantivy::schema::FAST,
+                    }
                 }
             }
-            if let Some(tokenizer) = tokenizer {
+        )*
+    }
+}
+
+impl_numeric_field_options!(NumericOptions, DateOptions, IpAddrOptions, BytesOptions);
+
+fn numeric_options<T: NumericFieldOptions>(flags: &Option<Vec<NumericOption>>) -> T {
+    flags.iter().flatten().fold(T::default(), |options, &flag| options.with_flag(flag))
+}
+
+fn text_options(
+    options: TextOptions,
+    flags: &Option<Vec<TextOption>>,
+    fast_tokenizer: &Option<String>,
+) -> TextOptions {
+    flags.iter().flatten().fold(options, |options, flag| match flag {
+        TextOption::STORED => options | tantivy::schema::STORED,
+        TextOption::FAST => options.set_fast(fast_tokenizer.as_deref()),
+    })
+}
+
+fn check_fast_tokenizer(
+    name: &str,
+    flags: &Option<Vec<TextOption>>,
+    fast_tokenizer: &Option<String>,
+) -> Result<(), Error> {
+    let fast = flags.iter().flatten().any(|flag| matches!(flag, TextOption::FAST));
+    if fast_tokenizer.is_some() && !fast {
+        return Err(format!("field '{name}': 'fastTokenizer' requires the FAST flag").into());
+    }
+    Ok(())
+}
+
+fn add_field(builder: &mut SchemaBuilder, name: &str, options: &FieldDescriptor) -> Result<(), Error> {
+    match options {
+        FieldDescriptor::Text { flags, indexed, tokenizer, index, fieldnorms, fast_tokenizer } => {
+            check_fast_tokenizer(name, flags, fast_tokenizer)?;
+            let mut options = text_options(TextOptions::default(), flags, fast_tokenizer);
+            if indexed.unwrap_or(true) {
+                // Defaults match Tantivy's `TEXT` options, except that a custom
+                // tokenizer has always defaulted to `BASIC` in these bindings.
+                let index_option = match (index, tokenizer) {
+                    (Some(index), _) => (*index).into(),
+                    (None, Some(_)) => tantivy::schema::IndexRecordOption::Basic,
+                    (None, None) => tantivy::schema::IndexRecordOption::WithFreqsAndPositions,
+                };
+                let text_field_indexing = TextFieldIndexing::default()
+                    .set_tokenizer(tokenizer.as_deref().unwrap_or("default"))
+                    .set_index_option(index_option)
+                    .set_fieldnorms(fieldnorms.unwrap_or(true));
+                options = options.set_indexing_options(text_field_indexing);
+            } else if tokenizer.is_some() || index.is_some() || fieldnorms.is_some() {
+                return Err(format!(
+                    "field '{name}': 'tokenizer', 'index' and 'fieldnorms' only apply to indexed fields"
+                ).into());
+            }
+            builder.add_text_field(name, options);
+        }
+        FieldDescriptor::String { flags, fast_tokenizer } => {
+            check_fast_tokenizer(name, flags, fast_tokenizer)?;
+            builder.add_text_field(name, text_options(tantivy::schema::STRING, flags, fast_tokenizer));
+        }
+        FieldDescriptor::F64 { flags } => {
+            builder.add_f64_field(name, numeric_options::<NumericOptions>(flags));
+        }
+        FieldDescriptor::I64 { flags } => {
+            builder.add_i64_field(name, numeric_options::<NumericOptions>(flags));
+        }
+        FieldDescriptor::U64 { flags } => {
+            builder.add_u64_field(name, numeric_options::<NumericOptions>(flags));
+        }
+        FieldDescriptor::Date { flags, precision } => {
+            let options = numeric_options::<DateOptions>(flags)
+                .set_precision(precision.unwrap_or_default().into());
+            builder.add_date_field(name, options);
+        }
+        FieldDescriptor::Bool { flags } => {
+            builder.add_bool_field(name, numeric_options::<NumericOptions>(flags));
+        }
+        FieldDescriptor::IpAddr { flags } => {
+            builder.add_ip_addr_field(name, numeric_options::<IpAddrOptions>(flags));
+        }
+        FieldDescriptor::Bytes { flags } => {
+            builder.add_bytes_field(name, numeric_options::<BytesOptions>(flags));
+        }
+        FieldDescriptor::Json { flags, indexed, tokenizer, index, fast_tokenizer, expand_dots } => {
+            let mut options = JsonObjectOptions::default();
+            if indexed.unwrap_or(true) {
                 let index_option = match index {
                     Some(index) => (*index).into(),
-                    None => tantivy::schema::IndexRecordOption::Basic,
+                    None => tantivy::schema::IndexRecordOption::WithFreqsAndPositions,
                 };
                 let text_field_indexing = TextFieldIndexing::default()
-                    .set_tokenizer(&tokenizer)
+                    .set_tokenizer(tokenizer.as_deref().unwrap_or("default"))
                     .set_index_option(index_option);
                 options = options.set_indexing_options(text_field_indexing);
             }
-            builder.add_text_field(name, options);
-        }
-        FieldDescriptor::String { flags } => {
-            let mut options = TextOptions::default() | tantivy::schema::STRING;
             if let Some(flags) = flags {
                 for flag in flags {
                     options = match flag {
-                        TextOption::STORED => options | tantivy::schema::STORED,
+                        JsonOption::STORED => options.set_stored(),
+                        JsonOption::FAST => options.set_fast(fast_tokenizer.as_deref()),
                     };
                 }
             }
-            builder.add_text_field(name, options);
+            if expand_dots.unwrap_or(false) {
+                options = options.set_expand_dots_enabled();
+            }
+            builder.add_json_field(name, options);
         }
-        FieldDescriptor::F64 { flags } => {
-            let mut options = NumericOptions::default();
+        FieldDescriptor::Facet { flags } => {
+            let mut options = FacetOptions::default();
             if let Some(flags) = flags {
                 for flag in flags {
                     options = match flag {
-                        NumericOption::STORED => options | tantivy::schema::STORED,
-                        NumericOption::INDEXED => options | tantivy::schema::INDEXED,
+                        FacetOption::STORED => options.set_stored(),
                     };
                 }
             }
-            builder.add_f64_field(name, options);
+            builder.add_facet_field(name, options);
+        }
+    }
+    Ok(())
+}
+
+fn numeric_flags(stored: bool, indexed: bool, fast: bool) -> Option<Vec<NumericOption>> {
+    let mut flags = vec![];
+    if stored {
+        flags.push(NumericOption::STORED);
+    }
+    if indexed {
+        flags.push(NumericOption::INDEXED);
+    }
+    if fast {
+        flags.push(NumericOption::FAST);
+    }
+    (!flags.is_empty()).then_some(flags)
+}
+
+fn text_flags(stored: bool, fast: bool) -> Option<Vec<TextOption>> {
+    let mut flags = vec![];
+    if stored {
+        flags.push(TextOption::STORED);
+    }
+    if fast {
+        flags.push(TextOption::FAST);
+    }
+    (!flags.is_empty()).then_some(flags)
+}
+
+/// Reconstructs the descriptor of a field from its Tantivy field type. This is
+/// the inverse of `add_field`, used to describe the schema of an index that
+/// was opened from disk.
+fn field_descriptor(field_type: &FieldType) -> FieldDescriptor {
+    match field_type {
+        FieldType::Str(options) => {
+            let flags = text_flags(options.is_stored(), options.is_fast());
+            let fast_tokenizer = options.get_fast_field_tokenizer_name().map(str::to_string);
+            match options.get_indexing_options() {
+                // Matches Tantivy's `STRING` options.
+                Some(indexing)
+                    if indexing.tokenizer() == "raw"
+                        && indexing.index_option() == tantivy::schema::IndexRecordOption::Basic
+                        && indexing.fieldnorms() =>
+                {
+                    FieldDescriptor::String { flags, fast_tokenizer }
+                }
+                Some(indexing) => FieldDescriptor::Text {
+                    flags,
+                    indexed: None,
+                    tokenizer: Some(indexing.tokenizer().to_string()),
+                    index: Some(indexing.index_option().into()),
+                    fieldnorms: Some(indexing.fieldnorms()),
+                    fast_tokenizer,
+                },
+                None => FieldDescriptor::Text {
+                    flags,
+                    indexed: Some(false),
+                    tokenizer: None,
+                    index: None,
+                    fieldnorms: None,
+                    fast_tokenizer,
+                },
+            }
+        }
+        FieldType::F64(options) => FieldDescriptor::F64 {
+            flags: numeric_flags(options.is_stored(), options.is_indexed(), options.is_fast()),
+        },
+        FieldType::I64(options) => FieldDescriptor::I64 {
+            flags: numeric_flags(options.is_stored(), options.is_indexed(), options.is_fast()),
+        },
+        FieldType::U64(options) => FieldDescriptor::U64 {
+            flags: numeric_flags(options.is_stored(), options.is_indexed(), options.is_fast()),
+        },
+        FieldType::Date(options) => FieldDescriptor::Date {
+            flags: numeric_flags(options.is_stored(), options.is_indexed(), options.is_fast()),
+            precision: Some(options.get_precision().into()),
+        },
+        FieldType::Bool(options) => FieldDescriptor::Bool {
+            flags: numeric_flags(options.is_stored(), options.is_indexed(), options.is_fast()),
+        },
+        FieldType::IpAddr(options) => FieldDescriptor::IpAddr {
+            flags: numeric_flags(options.is_stored(), options.is_indexed(), options.is_fast()),
+        },
+        FieldType::Bytes(options) => FieldDescriptor::Bytes {
+            flags: numeric_flags(options.is_stored(), options.is_indexed(), options.is_fast()),
+        },
+        FieldType::JsonObject(options) => {
+            let mut flags = vec![];
+            if options.is_stored() {
+                flags.push(JsonOption::STORED);
+            }
+            if options.is_fast() {
+                flags.push(JsonOption::FAST);
+            }
+            let indexing = options.get_text_indexing_options();
+            FieldDescriptor::Json {
+                flags: (!flags.is_empty()).then_some(flags),
+                indexed: indexing.is_none().then_some(false),
+                tokenizer: indexing.map(|indexing| indexing.tokenizer().to_string()),
+                index: indexing.map(|indexing| indexing.index_option().into()),
+                fast_tokenizer: options.get_fast_field_tokenizer_name().map(str::to_string),
+                expand_dots: Some(options.is_expand_dots_enabled()),
+            }
         }
+        FieldType::Facet(options) => FieldDescriptor::Facet {
+            flags: options.is_stored().then(|| vec![FacetOption::STORED]),
+        },
     }
 }
 
@@ -330,169 +819,1267 @@ fn add_field(builder: &mut SchemaBuilder, name: &str, options: &FieldDescriptor)
 struct Schema {
     schema: RefCell<t::Schema>,
     fields: OrderMap<String, FieldDescriptor>,
+    key: Option<String>,
 }
 
-#[neon::export(class)]
 impl Schema {
-    fn new(Json(fields): Json<OrderMap<String, FieldDescriptor>>) -> Self {
-        let mut builder = t::Schema::builder();
-        for (field_name, options) in fields.iter() {
-            add_field(&mut builder, field_name, options);
-        }
+    fn from_tantivy(schema: t::Schema, key: Option<String>) -> Self {
+        let fields = schema
+            .fields()
+            .map(|(_, entry)| (entry.name().to_string(), field_descriptor(entry.field_type())))
+            .collect();
         Self {
-            schema: RefCell::new(builder.build()),
-            fields: fields,
+            schema: RefCell::new(schema),
+            fields,
+            key,
         }
     }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct SchemaOptions {
+    key: Option<String>,
+}
+
+#[neon::export(class)]
+impl Schema {
+    fn new(
+        Json(fields): Json<OrderMap<String, FieldDescriptor>>,
+        options: Option<Json<SchemaOptions>>,
+    ) -> Result<Self, impl Throwable> {
+        coded(Box::new(|| {
+            let mut builder = t::Schema::builder();
+            for (field_name, options) in fields.iter() {
+                add_field(&mut builder, field_name, options)?;
+            }
+            let schema = builder.build();
+            let Json(options) = options.unwrap_or(Json(SchemaOptions::default()));
+            let key = options.key;
+            if let Some(key) = &key {
+                key_field(&schema, key)?;
+            }
+            Ok(Self {
+                schema: RefCell::new(schema),
+                fields: fields,
+                key,
+            })
+        }))
+    }
 
     fn fields(&self) -> Json<OrderMap<String, FieldDescriptor>> {
         Json(self.fields.clone())
     }
+
+    fn key(&self) -> Json<Option<String>> {
+        Json(self.key.clone())
+    }
+}
+
+/// Resolves a field that uniquely identifies documents, which must be indexed
+/// so that existing documents can be deleted by their key.
+fn key_field(schema: &t::Schema, name: &str) -> Result<Field, Error> {
+    let field = schema.get_field(name)?;
+    match schema.get_field_entry(field).field_type() {
+        FieldType::JsonObject(_) => Err(format!("JSON field '{name}' cannot be a key field").into()),
+        // A tokenized key would match every document sharing one of its tokens.
+        FieldType::Str(options)
+            if options.get_indexing_options().is_some_and(|indexing| indexing.tokenizer() != "raw") =>
+        {
+            Err(format!("key field '{name}' must be a string field, not a tokenized text field").into())
+        }
+        field_type if !field_type.is_indexed() => Err(format!("key field '{name}' is not indexed").into()),
+        _ => Ok(field),
+    }
+}
+
+/// The file that records the `SchemaOptions` of an index, which Tantivy
+/// doesn't store in its own metadata. It's read and written through the
+/// unmanaged directory so that Tantivy's garbage collection leaves it alone.
+const SCHEMA_OPTIONS_FILE: &str = "tantivy-node.json";
+
+fn read_schema_options(dir: &dyn Directory) -> Result<SchemaOptions, Error> {
+    let path = Path::new(SCHEMA_OPTIONS_FILE);
+    if !dir.exists(path)? {
+        return Ok(SchemaOptions::default());
+    }
+    Ok(serde_json::from_slice(&dir.atomic_read(path)?)?)
+}
+
+fn create_index(dir: Box<dyn Directory>, schema: t::Schema, key: &Option<String>) -> Result<t::Index, Error> {
+    let options = SchemaOptions { key: key.clone() };
+    let json = serde_json::to_vec(&options)?;
+    let index = t::Index::create(dir.box_clone(), schema, IndexSettings::default())?;
+    dir.atomic_write(Path::new(SCHEMA_OPTIONS_FILE), &json)?;
+    Ok(index)
 }
 
+/// The Tantivy searcher of a JS searcher, which is released when its index
+/// is closed so that the segment files it maps can be unmapped.
+type SearcherSlot = Mutex<Option<Arc<t::Searcher>>>;
+
 #[derive(Clone)]
 struct Searcher {
-    searcher: Arc<t::Searcher>,
+    searcher: Arc<SearcherSlot>,
 }
 
 impl Searcher {
-    fn interpret_field(&self, field: &str) -> Result<Field, Error> {
-        Ok(self.searcher
-            .index()
-            .schema()
-            .get_field(field)?)
+    /// Returns the underlying searcher, unless its index has been closed.
+    fn searcher(&self) -> Result<Arc<t::Searcher>, Error> {
+        self.searcher
+            .lock()?
+            .clone()
+            .ok_or_else(index_closed)
     }
-}
 
-#[neon::export(class)]
-impl Searcher {
-    fn new(
-        index: Arc<OpenIndex>,
-    ) -> Result<Self, Error> {
-        Ok(Self {
-            searcher: Arc::new(index.reader.lock().map_err(|_| "mutex poisoned")?.searcher()),
-        })
+    fn schema(&self) -> Result<t::Schema, Error> {
+        Ok(self.searcher()?.schema().clone())
     }
 
-    fn term_query(
-        &self,
-        term: String,
-        field: String,
-        options: Option<Json<IndexRecordOption>>,
-    ) -> Result<Query, Error> {
-        let field = self.interpret_field(&field)?;
-        let term = Term::from_field_text(field, &term);
-        let Json(options) = options.unwrap_or(Json(IndexRecordOption::default()));
-        let query = TermQuery::new(term, options.into());
-        Ok(Query { query: Arc::new(Box::new(query)) })
+    fn interpret_field(&self, field: &str) -> Result<Field, Error> {
+        Ok(self.schema()?.get_field(field)?)
     }
 
-    fn phrase_query(
-        &self,
-        Json(terms): Json<Vec<String>>,
-        field: String,
-    ) -> Result<Query, Error> {
-        let field = self.interpret_field(&field)?;
-        let terms = terms.into_iter().map(|term| {
-            Term::from_field_text(field, &term)
-        }).collect();
-        let query = PhraseQuery::new(terms);
-        Ok(Query { query: Arc::new(Box::new(query)) })
+    fn top_docs(&self, query: &Query, options: SearchOptions) -> Result<SearchResults, Error> {
+        if options.top.is_nan() || options.top < 1.0 {
+            let message = format!("'top' must be at least 1, got {}", options.top);
+            return Err(Error::range_error(ErrorCode::InvalidArgument, message));
+        }
+        let collector = TopDocs::with_limit(options.top as usize);
+        let top_docs = self.searcher()?.search(query.query.as_ref(), &collector)?;
+        self.results(query, top_docs, options.dates)
     }
 
-    fn fuzzy_term_query(
-        &self,
-        term: String,
-        field: String,
-        options: Option<Json<FuzzyTermQueryOptions>>,
-    ) -> Result<Query, Error> {
-        let field = self.interpret_field(&field)?;
-        let term = Term::from_field_text(field, &term);
-        let Json(options) = options.unwrap_or(Json(FuzzyTermQueryOptions::default()));
-        let query = if options.is_prefix {
-            FuzzyTermQuery::new_prefix(term, options.max_distance as u8, options.transposition_costs_one)
-        } else {
-            FuzzyTermQuery::new(term, options.max_distance as u8, options.transposition_costs_one)
+    /// Resolves a path into a JSON field, such as `attrs.color`, returning
+    /// `None` if the path doesn't point into a JSON field.
+    fn interpret_json_path<'a>(&self, path: &'a str) -> Result<Option<(Field, &'a str, JsonObjectOptions)>, Error> {
+        let schema = self.schema()?;
+        let Some((field, path)) = schema.find_field(path) else {
+            return Ok(None);
         };
-        Ok(Query { query: Arc::new(Box::new(query)) })
+        match schema.get_field_entry(field).field_type() {
+            FieldType::JsonObject(options) => Ok(Some((field, path, options.clone()))),
+            _ => Ok(None),
+        }
     }
 
-    fn regexp_query(
+    /// Matches text at a path within a JSON field the same way Tantivy's query
+    /// parser does: the text is split with the field's tokenizer, and if it
+    /// also reads as a number, date or boolean, values of that type match too.
+    fn json_term_query(
         &self,
-        pattern: String,
-        field: String,
+        name: &str,
+        field: Field,
+        path: &str,
+        options: &JsonObjectOptions,
+        text: &str,
+        index_option: tantivy::schema::IndexRecordOption,
     ) -> Result<Query, Error> {
-        let field = self.interpret_field(&field)?;
-        let query = RegexQuery::from_pattern(&pattern, field)?;
-        Ok(Query { query: Arc::new(Box::new(query)) })
+        let Some(indexing) = options.get_text_indexing_options() else {
+            return Err(format!("field '{name}' is not indexed").into());
+        };
+        let mut queries: Vec<(t::Occur, Box<dyn t::Query>)> = vec![];
+        if let Some(term) = json_fast_value_term(field, path, options, text) {
+            queries.push((t::Occur::Should, Box::new(TermQuery::new(term, index_option))));
+        }
+        let mut analyzer = self.searcher()?.index().tokenizer_for_field(field)?;
+        let mut terms = vec![];
+        analyzer.token_stream(text).process(&mut |token| {
+            terms.push((token.position, json_text_term(field, path, options, &token.text)));
+        });
+        match terms.len() {
+            0 => {}
+            1 => {
+                let (_, term) = terms.remove(0);
+                queries.push((t::Occur::Should, Box::new(TermQuery::new(term, index_option))));
+            }
+            _ => {
+                if !indexing.index_option().has_positions() {
+                    return Err(format!(
+                        "'{text}' contains several terms, but field '{name}' doesn't index positions for phrase matching"
+                    ).into());
+                }
+                queries.push((t::Occur::Should, Box::new(PhraseQuery::new_with_offset(terms))));
+            }
+        }
+        let query: Box<dyn t::Query> = match queries.len() {
+            0 => Box::new(EmptyQuery),
+            1 => queries.remove(0).1,
+            _ => Box::new(BooleanQuery::new(queries)),
+        };
+        Ok(Query { query: Arc::new(query) })
     }
 
-    fn phrase_prefix_query(
+    /// Builds the terms that a value matches at a path within a JSON field:
+    /// a number, date or boolean term if the text reads as one, and the text
+    /// itself as tokenized for the field. Text that tokenizes into several
+    /// terms can only be matched as a phrase, so it's rejected.
+    fn json_terms(
         &self,
-        Json(terms): Json<Vec<String>>,
-        field: String,
-    ) -> Result<Query, Error> {
-        let field = self.interpret_field(&field)?;
-        let terms = terms.into_iter().map(|term| {
-            Term::from_field_text(field, &term)
-        }).collect();
-        let query = PhrasePrefixQuery::new(terms);
-        Ok(Query { query: Arc::new(Box::new(query)) })
+        name: &str,
+        field: Field,
+        path: &str,
+        options: &JsonObjectOptions,
+        text: &str,
+    ) -> Result<Vec<Term>, Error> {
+        if options.get_text_indexing_options().is_none() {
+            return Err(format!("field '{name}' is not indexed").into());
+        }
+        let mut terms: Vec<Term> = json_fast_value_term(field, path, options, text).into_iter().collect();
+        let mut analyzer = self.searcher()?.index().tokenizer_for_field(field)?;
+        let mut tokens = vec![];
+        analyzer.token_stream(text).process(&mut |token| tokens.push(token.text.clone()));
+        match tokens.as_slice() {
+            [] => {}
+            [token] => terms.push(json_text_term(field, path, options, token)),
+            _ => {
+                return Err(format!(
+                    "'{text}' contains several terms, so it can't be matched as a term of field '{name}'"
+                ).into());
+            }
+        }
+        Ok(terms)
     }
 
-    fn search_sync(
+    fn results(
         &self,
         query: &Query,
-        Json(options): Json<Option<SearchOptions>>,
-    ) -> Json<Vec<(Score, String, Explanation)>>{
-        let index = self.searcher.index();
-        let schema = index.schema();
-        let options = options.unwrap_or_default();
-        let collector = TopDocs::with_limit(options.top as usize);
-        Json(
-            self.searcher
-                .search(query.query.as_ref(), &collector)
-                .unwrap()
-                .iter()
-                .map(|&(score, doc_address)| {
-                    let retrieved_doc: TantivyDocument = self.searcher.doc(doc_address).unwrap();
-                    (score, retrieved_doc.to_json(&schema), query.query.explain(&self.searcher, doc_address).unwrap())
-                })
-                .collect::<Vec<_>>()
-        )
-    }
-
-    #[neon(task)]
-    fn search(
-        self,
-        query: Query,
-        options: Json<Option<SearchOptions>>,
-    ) -> Json<Vec<(Score, String, Explanation)>>{
-        self.search_sync(&query, options)
+        top_docs: Vec<(Score, DocAddress)>,
+        dates: doc::DateFormat,
+    ) -> Result<SearchResults, Error> {
+        let searcher = self.searcher()?;
+        let schema = searcher.schema();
+        let mut results = Vec::with_capacity(top_docs.len());
+        for (score, doc_address) in top_docs {
+            let retrieved_doc: TantivyDocument = searcher.doc(doc_address)?;
+            results.push(SearchResult {
+                score,
+                doc: doc::Document::new(&retrieved_doc, schema, dates),
+                explanation: query.query.explain(&searcher, doc_address)?,
+            });
+        }
+        Ok(SearchResults(results))
     }
 
-    fn search_terms(
+    fn facet_counts(
         &self,
-        field: String,
-        pattern: String,
-    ) -> Result<Json<Vec<String>>, Error>
-    {
-        let readers = self.searcher.segment_readers();
-        let field = self.interpret_field(&field)?;
-        let mut result = vec![];
-        for reader in readers {
-            let inverted_index = reader.inverted_index(field)?;
-            let dict = inverted_index.terms();
-            let mut stream = dict.search(Regex::new(&pattern)?).into_stream()?;
-            while let Some((term, _)) = stream.next() {
-                let term = String::from_utf8_lossy(term);
-                result.push(term.to_string());
+        query: &Query,
+        field: &str,
+        prefixes: Vec<String>,
+        options: SearchOptions,
+    ) -> Result<FacetSearchResults, Error> {
+        let schema = self.schema()?;
+        let field_type = schema.get_field_entry(self.interpret_field(field)?).field_type();
+        if !matches!(field_type, FieldType::Facet(_)) {
+            return Err(Error::type_error(ErrorCode::SchemaMismatch, format!(
+                "facet search is not supported on {} field '{field}'",
+                field_type.value_type().name(),
+            )));
+        }
+        let prefixes = prefixes
+            .iter()
+            .map(Facet::from_text)
+            .collect::<Result<Vec<_>, _>>()?;
+        // FacetCollector panics on overlapping facets, so reject them up front.
+        for (i, a) in prefixes.iter().enumerate() {
+            for b in &prefixes[i + 1..] {
+                if a.is_prefix_of(b) || b.is_prefix_of(a) {
+                    return Err(format!("facet prefixes cannot overlap: {a} and {b}").into());
+                }
             }
         }
-        Ok(Json(result))
+        let mut facet_collector = FacetCollector::for_field(field);
+        for prefix in &prefixes {
+            facet_collector.add_facet(prefix.clone());
+        }
+        // A `top` of zero collects facet counts without any top documents.
+        let (top_docs, counts) = match options.top as usize {
+            0 => (vec![], self.searcher()?.search(query.query.as_ref(), &facet_collector)?),
+            top => {
+                let collector = (TopDocs::with_limit(top), facet_collector);
+                self.searcher()?.search(query.query.as_ref(), &collector)?
+            }
+        };
+        let facets = prefixes
+            .into_iter()
+            .map(|prefix| {
+                let children = counts
+                    .get(prefix.clone())
+                    .map(|(facet, count)| (facet.to_string(), count))
+                    .collect();
+                (prefix.to_string(), children)
+            })
+            .collect();
+        Ok(FacetSearchResults {
+            hits: self.results(query, top_docs, options.dates)?,
+            facets,
+        })
+    }
+
+    fn interpret_indexed_field(&self, field: &str) -> Result<Field, Error> {
+        let f = self.interpret_field(field)?;
+        let schema = self.schema()?;
+        if !schema.get_field_entry(f).is_indexed() {
+            return Err(Error::new(ErrorCode::SchemaMismatch, format!("field '{field}' is not indexed")));
+        }
+        Ok(f)
+    }
+
+    /// Interprets a field for a query that matches token positions, which
+    /// requires the field to be indexed `WITH_FREQS_AND_POSITIONS`.
+    fn interpret_positional_field(&self, field: &str) -> Result<Field, Error> {
+        let f = self.interpret_indexed_field(field)?;
+        let schema = self.schema()?;
+        match schema.get_field_entry(f).field_type().index_record_option() {
+            Some(option) if option.has_positions() => Ok(f),
+            _ => Err(Error::new(ErrorCode::SchemaMismatch, format!(
+                "field '{field}' is not indexed with positions; phrase queries require \
+                 the 'WITH_FREQS_AND_POSITIONS' index option"
+            ))),
+        }
+    }
+
+    /// Creates a query parser that tokenizes terms with the tokenizers
+    /// registered on the index.
+    fn query_parser(&self, options: &ParseQueryOptions) -> Result<QueryParser, Error> {
+        let index = self.searcher()?.index().clone();
+        let schema = index.schema();
+        let default_fields = if options.default_fields.is_empty() {
+            schema
+                .fields()
+                .filter(|(_, entry)| entry.is_indexed() && matches!(entry.field_type(), FieldType::Str(_)))
+                .map(|(field, _)| field)
+                .collect()
+        } else {
+            options
+                .default_fields
+                .iter()
+                .map(|field| self.interpret_indexed_field(field))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+        let mut parser = QueryParser::for_index(&index, default_fields);
+        if options.conjunction_by_default {
+            parser.set_conjunction_by_default();
+        }
+        for (field, boost) in &options.field_boosts {
+            parser.set_field_boost(self.interpret_field(field)?, *boost as Score);
+        }
+        for (field, fuzzy) in &options.fuzzy {
+            let distance: u8 = fuzzy.max_distance.try_into()?;
+            parser.set_field_fuzzy(
+                self.interpret_indexed_field(field)?,
+                fuzzy.is_prefix,
+                distance,
+                fuzzy.transposition_costs_one,
+            );
+        }
+        Ok(parser)
+    }
+
+    /// Interprets the query argument of a search, which may be a `Query` or
+    /// a query string to parse with the default fields of the search.
+    fn query_arg<'cx>(
+        &self,
+        cx: &mut Cx<'cx>,
+        query: Handle<'cx, JsValue>,
+        options: &SearchOptions,
+    ) -> NeonResult<Query> {
+        let Ok(text) = query.downcast::<JsString, _>(cx) else {
+            return query_from_js(cx, query);
+        };
+        let text = text.value(cx);
+        let options = ParseQueryOptions {
+            default_fields: options.default_fields.clone(),
+            ..ParseQueryOptions::default()
+        };
+        match self.query_parser(&options).and_then(|parser| Ok(parser.parse_query(&text)?)) {
+            Ok(query) => Ok(Query { query: Arc::new(query) }),
+            Err(err) => err.throw(cx),
+        }
+    }
+
+    fn interpret_field_type<'cx>(
+        &self,
+        cx: &mut Cx<'cx>,
+        field: &str,
+    ) -> NeonResult<(Field, FieldType)> {
+        let schema = match self.schema() {
+            Ok(schema) => schema,
+            Err(err) => return err.throw(cx),
+        };
+        match schema.get_field(field) {
+            Ok(f) => Ok((f, schema.get_field_entry(f).field_type().clone())),
+            Err(err) => Error::from(err).throw(cx),
+        }
+    }
+
+    /// Builds a term for an indexed field from a JS value, which is
+    /// interpreted according to the type of the field.
+    fn typed_term<'cx>(
+        &self,
+        cx: &mut Cx<'cx>,
+        field: &str,
+        field_id: Field,
+        field_type: &FieldType,
+        value: Handle<'cx, JsValue>,
+    ) -> NeonResult<Term> {
+        if !field_type.is_indexed() {
+            return Error::new(ErrorCode::SchemaMismatch, format!("field '{field}' is not indexed")).throw(cx);
+        }
+        let value = doc::value_from_js(cx, field, field_type, value)?;
+        match doc::term_from_value(field_id, &value) {
+            Some(term) => Ok(term),
+            None => Error::type_error(ErrorCode::SchemaMismatch, format!(
+                "term queries are not supported on {} field '{field}'",
+                field_type.value_type().name(),
+            )).throw(cx),
+        }
+    }
+}
+
+#[neon::export(class)]
+impl Searcher {
+    fn new(
+        index: Arc<OpenIndex>,
+    ) -> Result<Self, impl Throwable> {
+        coded(Box::new(|| index.searcher()))
+    }
+
+    /// Matches a term, which is interpreted according to the type of the
+    /// field: a number for a numeric field, a date for a date field, and so
+    /// on. On an IP address field, a CIDR block such as `"10.0.0.0/8"`
+    /// matches every address in the block.
+    fn term_query<'cx>(
+        &self,
+        cx: &mut FunctionContext<'cx>,
+        term: Handle<'cx, JsValue>,
+        field: String,
+        options: Option<Json<IndexRecordOption>>,
+    ) -> NeonResult<Query> {
+        let Json(options) = options.unwrap_or(Json(IndexRecordOption::default()));
+        let json_path = match self.interpret_json_path(&field) {
+            Ok(json_path) => json_path,
+            Err(err) => return err.throw(cx),
+        };
+        if let Some((json_field, path, json_options)) = json_path {
+            let text = json_term_text(cx, &field, term)?;
+            return match self.json_term_query(&field, json_field, path, &json_options, &text, options.into()) {
+                Ok(query) => Ok(query),
+                Err(err) => err.throw(cx),
+            };
+        }
+        let (field_id, field_type) = self.interpret_field_type(cx, &field)?;
+        if let (FieldType::IpAddr(_), Ok(cidr)) = (&field_type, term.downcast::<JsString, _>(cx)) {
+            let cidr = cidr.value(cx);
+            if cidr.contains('/') && field_type.is_indexed() {
+                let (lower, upper) = match parse_ip_range(&cidr) {
+                    Ok(range) => range,
+                    Err(err) => return err.throw(cx),
+                };
+                let query = RangeQuery::new_ip_bounds(field, Bound::Included(lower), Bound::Included(upper));
+                return Ok(Query { query: Arc::new(Box::new(query)) });
+            }
+        }
+        let term = self.typed_term(cx, &field, field_id, &field_type, term)?;
+        let query = TermQuery::new(term, options.into());
+        Ok(Query { query: Arc::new(Box::new(query)) })
+    }
+
+    fn phrase_query(
+        &self,
+        Json(terms): Json<Vec<String>>,
+        field: String,
+    ) -> Result<Query, impl Throwable> {
+        coded(Box::new(|| {
+            let field = self.interpret_positional_field(&field)?;
+            let terms = terms.into_iter().map(|term| {
+                Term::from_field_text(field, &term)
+            }).collect();
+            let query = PhraseQuery::new(terms);
+            Ok(Query { query: Arc::new(Box::new(query)) })
+        }))
+    }
+
+    fn fuzzy_term_query(
+        &self,
+        term: String,
+        field: String,
+        options: Option<Json<FuzzyTermQueryOptions>>,
+    ) -> Result<Query, impl Throwable> {
+        coded(Box::new(|| {
+            let field = self.interpret_indexed_field(&field)?;
+            let term = Term::from_field_text(field, &term);
+            let Json(options) = options.unwrap_or(Json(FuzzyTermQueryOptions::default()));
+            let query = if options.is_prefix {
+                FuzzyTermQuery::new_prefix(term, options.max_distance as u8, options.transposition_costs_one)
+            } else {
+                FuzzyTermQuery::new(term, options.max_distance as u8, options.transposition_costs_one)
+            };
+            Ok(Query { query: Arc::new(Box::new(query)) })
+        }))
+    }
+
+    fn regexp_query(
+        &self,
+        pattern: String,
+        field: String,
+    ) -> Result<Query, impl Throwable> {
+        coded(Box::new(|| {
+            let field = self.interpret_indexed_field(&field)?;
+            let query = RegexQuery::from_pattern(&pattern, field)
+                .map_err(|err| Error::new(ErrorCode::QueryParse, err.to_string()))?;
+            Ok(Query { query: Arc::new(Box::new(query)) })
+        }))
+    }
+
+    fn phrase_prefix_query(
+        &self,
+        Json(terms): Json<Vec<String>>,
+        field: String,
+    ) -> Result<Query, impl Throwable> {
+        coded(Box::new(|| {
+            let field = self.interpret_positional_field(&field)?;
+            let terms = terms.into_iter().map(|term| {
+                Term::from_field_text(field, &term)
+            }).collect();
+            let query = PhrasePrefixQuery::new(terms);
+            Ok(Query { query: Arc::new(Box::new(query)) })
+        }))
+    }
+
+    fn range_query<'cx>(
+        &self,
+        cx: &mut FunctionContext<'cx>,
+        field: String,
+        bounds: Handle<'cx, JsObject>,
+    ) -> NeonResult<Query> {
+        let (schema, json_path) = match self.schema().and_then(|schema| Ok((schema, self.interpret_json_path(&field)?))) {
+            Ok(result) => result,
+            Err(err) => return err.throw(cx),
+        };
+        if let Some((json_field, path, json_options)) = json_path {
+            let (lower, upper) = json_range_bounds(cx, &field, json_field, path, &json_options, bounds)?;
+            let field_name = schema.get_field_name(json_field).to_string();
+            let query = RangeQuery::new_term_bounds(field_name, Type::Json, &lower, &upper);
+            return Ok(Query { query: Arc::new(Box::new(query)) });
+        }
+        let (_, field_type) = self.interpret_field_type(cx, &field)?;
+        let query = match field_type {
+            FieldType::Date(_) => {
+                let (lower, upper) = typed_range_bounds(cx, &field, &field_type, bounds, |value| match value {
+                    OwnedValue::Date(date) => Some(date),
+                    _ => None,
+                })?;
+                RangeQuery::new_date_bounds(field, lower, upper)
+            }
+            FieldType::IpAddr(_) => {
+                let (lower, upper) = typed_range_bounds(cx, &field, &field_type, bounds, |value| match value {
+                    OwnedValue::IpAddr(addr) => Some(addr),
+                    _ => None,
+                })?;
+                RangeQuery::new_ip_bounds(field, lower, upper)
+            }
+            FieldType::I64(_) => {
+                let (lower, upper) = typed_range_bounds(cx, &field, &field_type, bounds, |value| match value {
+                    OwnedValue::I64(n) => Some(n),
+                    _ => None,
+                })?;
+                RangeQuery::new_i64_bounds(field, lower, upper)
+            }
+            FieldType::U64(_) => {
+                let (lower, upper) = typed_range_bounds(cx, &field, &field_type, bounds, |value| match value {
+                    OwnedValue::U64(n) => Some(n),
+                    _ => None,
+                })?;
+                RangeQuery::new_u64_bounds(field, lower, upper)
+            }
+            FieldType::F64(_) => {
+                let (lower, upper) = typed_range_bounds(cx, &field, &field_type, bounds, |value| match value {
+                    OwnedValue::F64(n) => Some(n),
+                    _ => None,
+                })?;
+                RangeQuery::new_f64_bounds(field, lower, upper)
+            }
+            // Bounds of text fields aren't tokenized, so they are compared with
+            // the indexed terms as is, which suits fields indexed as `STRING`.
+            FieldType::Str(_) => {
+                let (lower, upper) = typed_range_bounds(cx, &field, &field_type, bounds, |value| match value {
+                    OwnedValue::Str(text) => Some(text),
+                    _ => None,
+                })?;
+                let lower = lower.as_ref().map(String::as_str);
+                let upper = upper.as_ref().map(String::as_str);
+                RangeQuery::new_str_bounds(field, lower, upper)
+            }
+            _ => {
+                let err = Error::new(ErrorCode::SchemaMismatch, format!(
+                    "range queries are not supported on {} field '{field}'",
+                    field_type.value_type().name(),
+                ));
+                return err.throw(cx);
+            }
+        };
+        Ok(Query { query: Arc::new(Box::new(query)) })
+    }
+
+    /// Matches every document.
+    fn all_query(&self) -> Result<Query, impl Throwable> {
+        coded(Box::new(|| {
+            self.searcher()?;
+            Ok(Query { query: Arc::new(Box::new(AllQuery)) })
+        }))
+    }
+
+    /// Matches the documents that have a value for a field, which must be a
+    /// fast field.
+    fn exists_query(&self, field: String) -> Result<Query, impl Throwable> {
+        coded(Box::new(move || {
+            let f = self.interpret_field(&field)?;
+            if !self.schema()?.get_field_entry(f).is_fast() {
+                return Err(Error::new(ErrorCode::SchemaMismatch, format!(
+                    "field '{field}' is not a fast field; exists queries require the 'FAST' option"
+                )));
+            }
+            let query = ExistsQuery::new_exists_query(field);
+            Ok(Query { query: Arc::new(Box::new(query)) })
+        }))
+    }
+
+    /// Matches the documents that contain any of the terms, which are
+    /// interpreted according to the type of the field as in `termQuery`.
+    fn term_set_query<'cx>(
+        &self,
+        cx: &mut FunctionContext<'cx>,
+        field: String,
+        values: Handle<'cx, JsArray>,
+    ) -> NeonResult<Query> {
+        let json_path = match self.interpret_json_path(&field) {
+            Ok(json_path) => json_path,
+            Err(err) => return err.throw(cx),
+        };
+        let mut terms = vec![];
+        if let Some((json_field, path, json_options)) = json_path {
+            for value in values.to_vec(cx)? {
+                let text = json_term_text(cx, &field, value)?;
+                match self.json_terms(&field, json_field, path, &json_options, &text) {
+                    Ok(json_terms) => terms.extend(json_terms),
+                    Err(err) => return err.throw(cx),
+                }
+            }
+        } else {
+            let (field_id, field_type) = self.interpret_field_type(cx, &field)?;
+            for value in values.to_vec(cx)? {
+                terms.push(self.typed_term(cx, &field, field_id, &field_type, value)?);
+            }
+        }
+        let query = TermSetQuery::new(terms);
+        Ok(Query { query: Arc::new(Box::new(query)) })
+    }
+
+    /// Combines queries into one, according to whether each must, should or
+    /// must not match.
+    fn boolean_query<'cx>(
+        &self,
+        cx: &mut FunctionContext<'cx>,
+        clauses: Handle<'cx, JsArray>,
+        options: Option<Json<BooleanQueryOptions>>,
+    ) -> NeonResult<Query> {
+        if let Err(err) = self.searcher() {
+            return err.throw(cx);
+        }
+        let mut subqueries = vec![];
+        for clause in clauses.to_vec(cx)? {
+            let Ok(clause) = clause.downcast::<JsObject, _>(cx) else {
+                let message = "boolean query clauses must be objects with 'occur' and 'query'";
+                return Error::type_error(ErrorCode::InvalidArgument, message).throw(cx);
+            };
+            let occur = clause.get_value(cx, "occur")?;
+            let Ok(Json(occur)) = Json::<Occur>::try_from_js(cx, occur)? else {
+                let message = "boolean query clauses must occur as 'MUST', 'SHOULD' or 'MUST_NOT'";
+                return Error::type_error(ErrorCode::InvalidArgument, message).throw(cx);
+            };
+            let query = clause.get_value(cx, "query")?;
+            let query = query_from_js(cx, query)?;
+            subqueries.push((occur.into(), (**query.query).box_clone()));
+        }
+        let options = options.map(|Json(options)| options).unwrap_or_default();
+        let query = match options.minimum_should_match {
+            Some(minimum) => to_usize(minimum).and_then(|minimum| boolean::minimum_should_match_query(subqueries, minimum)),
+            None => Ok(boolean::boolean_query(subqueries)),
+        };
+        match query {
+            Ok(query) => Ok(Query { query: Arc::new(query) }),
+            Err(err) => err.throw(cx),
+        }
+    }
+
+    /// Multiplies the scores of a query by a factor, which can't be negative.
+    fn boost_query(&self, query: &Query, factor: f64) -> Result<Query, impl Throwable> {
+        coded(Box::new(move || {
+            self.searcher()?;
+            if factor.is_nan() || factor < 0.0 {
+                let message = format!("boost factor must be a non-negative number, got {factor}");
+                return Err(Error::range_error(ErrorCode::InvalidArgument, message));
+            }
+            let query = BoostQuery::new((**query.query).box_clone(), factor as Score);
+            Ok(Query { query: Arc::new(Box::new(query)) })
+        }))
+    }
+
+    /// Matches the documents of a query, giving them all the same score.
+    fn const_score_query(&self, query: &Query, score: f64) -> Result<Query, impl Throwable> {
+        coded(Box::new(move || {
+            self.searcher()?;
+            let query = ConstScoreQuery::new((**query.query).box_clone(), score as Score);
+            Ok(Query { query: Arc::new(Box::new(query)) })
+        }))
+    }
+
+    /// Matches the documents of any of the queries, scoring each document by
+    /// its best score plus the tie breaker times the sum of its other scores.
+    fn disjunction_max_query<'cx>(
+        &self,
+        cx: &mut FunctionContext<'cx>,
+        queries: Handle<'cx, JsArray>,
+        tie_breaker: Option<f64>,
+    ) -> NeonResult<Query> {
+        if let Err(err) = self.searcher() {
+            return err.throw(cx);
+        }
+        let tie_breaker = tie_breaker.unwrap_or(0.0);
+        if !(0.0..=1.0).contains(&tie_breaker) {
+            let message = format!("tie breaker must be between 0 and 1, got {tie_breaker}");
+            return Error::range_error(ErrorCode::InvalidArgument, message).throw(cx);
+        }
+        let mut disjuncts = vec![];
+        for query in queries.to_vec(cx)? {
+            let query = query_from_js(cx, query)?;
+            disjuncts.push(boolean::nestable((**query.query).box_clone()));
+        }
+        let query = DisjunctionMaxQuery::with_tie_breaker(disjuncts, tie_breaker as Score);
+        Ok(Query { query: Arc::new(Box::new(query)) })
+    }
+
+    /// Parses a query string in Tantivy's query language, such as
+    /// `title:fur AND year:[2000 TO *]`.
+    fn parse_query(
+        &self,
+        text: String,
+        options: Option<Json<ParseQueryOptions>>,
+    ) -> Result<Query, impl Throwable> {
+        coded(Box::new(move || {
+            let parser = self.query_parser(&options.map(|Json(options)| options).unwrap_or_default())?;
+            let query = parser.parse_query(&text)?;
+            Ok(Query { query: Arc::new(query) })
+        }))
+    }
+
+    /// Parses a query string, ignoring the parts that fail to parse instead
+    /// of throwing, and returns their errors alongside the query.
+    fn parse_query_lenient<'cx>(
+        &self,
+        cx: &mut FunctionContext<'cx>,
+        text: String,
+        options: Option<Json<ParseQueryOptions>>,
+    ) -> JsResult<'cx, JsObject> {
+        let parser = match self.query_parser(&options.map(|Json(options)| options).unwrap_or_default()) {
+            Ok(parser) => parser,
+            Err(err) => return err.throw(cx),
+        };
+        let (query, errors) = parser.parse_query_lenient(&text);
+        let object = cx.empty_object();
+        let query = Query { query: Arc::new(query) }.try_into_js(cx)?;
+        object.set(cx, "query", query)?;
+        let messages = JsArray::new(cx, errors.len());
+        for (i, err) in errors.into_iter().enumerate() {
+            let message = cx.string(err.to_string());
+            messages.set(cx, i as u32, message)?;
+        }
+        object.set(cx, "errors", messages)?;
+        Ok(object)
+    }
+
+    fn search_sync<'cx>(
+        &self,
+        cx: &mut FunctionContext<'cx>,
+        query: Handle<'cx, JsValue>,
+        options: Option<Handle<'cx, JsValue>>,
+    ) -> JsResult<'cx, JsArray> {
+        let options = options_from_js(cx, options)?;
+        let query = self.query_arg(cx, query, &options)?;
+        match self.top_docs(&query, options) {
+            Ok(results) => results.into_js(cx),
+            Err(err) => err.throw(cx),
+        }
+    }
+
+    /// Searches on a worker thread. Invalid arguments, including query
+    /// strings that fail to parse, reject the promise like any other error.
+    fn search<'cx>(
+        &self,
+        cx: &mut FunctionContext<'cx>,
+        query: Handle<'cx, JsValue>,
+        options: Option<Handle<'cx, JsValue>>,
+    ) -> JsResult<'cx, JsPromise> {
+        let args = cx.try_catch(|cx| {
+            let options = options_from_js(cx, options)?;
+            let query = self.query_arg(cx, query, &options)?;
+            Ok((query, options))
+        });
+        let (query, options) = match args {
+            Ok(args) => args,
+            Err(err) => return rejected(cx, err),
+        };
+        let searcher = self.clone();
+        let promise = cx
+            .task(move || searcher.top_docs(&query, options))
+            .promise(|mut cx, results: Result<SearchResults, Error>| match results {
+                Ok(results) => results.into_js(&mut cx),
+                Err(err) => err.throw(&mut cx),
+            });
+        Ok(promise)
+    }
+
+    fn facet_search_sync<'cx>(
+        &self,
+        cx: &mut FunctionContext<'cx>,
+        query: &Query,
+        field: String,
+        Json(prefixes): Json<Vec<String>>,
+        options: Option<Handle<'cx, JsValue>>,
+    ) -> JsResult<'cx, JsObject> {
+        let options = options_from_js(cx, options)?;
+        match self.facet_counts(query, &field, prefixes, options) {
+            Ok(results) => results.into_js(cx),
+            Err(err) => err.throw(cx),
+        }
+    }
+
+    fn facet_search<'cx>(
+        &self,
+        cx: &mut FunctionContext<'cx>,
+        query: &Query,
+        field: String,
+        Json(prefixes): Json<Vec<String>>,
+        options: Option<Handle<'cx, JsValue>>,
+    ) -> JsResult<'cx, JsPromise> {
+        let options = match cx.try_catch(|cx| options_from_js(cx, options)) {
+            Ok(options) => options,
+            Err(err) => return rejected(cx, err),
+        };
+        let searcher = self.clone();
+        let query = query.clone();
+        let promise = cx
+            .task(move || searcher.facet_counts(&query, &field, prefixes, options))
+            .promise(|mut cx, results: Result<FacetSearchResults, Error>| match results {
+                Ok(results) => results.into_js(&mut cx),
+                Err(err) => err.throw(&mut cx),
+            });
+        Ok(promise)
+    }
+
+    fn search_terms(
+        &self,
+        field: String,
+        pattern: String,
+    ) -> Result<Json<Vec<String>>, impl Throwable>
+    {
+        coded(Box::new(|| {
+            let searcher = self.searcher()?;
+            let readers = searcher.segment_readers();
+            let field = self.interpret_field(&field)?;
+            let mut result = vec![];
+            for reader in readers {
+                let inverted_index = reader.inverted_index(field)?;
+                let dict = inverted_index.terms();
+                let regex = Regex::new(&pattern).map_err(|err| Error::new(ErrorCode::QueryParse, err.to_string()))?;
+                let mut stream = dict.search(regex).into_stream()?;
+                while let Some((term, _)) = stream.next() {
+                    let term = String::from_utf8_lossy(term);
+                    result.push(term.to_string());
+                }
+            }
+            Ok(Json(result))
+        }))
+    }
+}
+
+/// Parses an IP address or CIDR block (e.g. `"10.0.0.0/8"`) into the inclusive
+/// range of addresses it covers. IPv4 addresses are mapped into IPv6 the same
+/// way Tantivy maps them when indexing.
+fn parse_ip_range(cidr: &str) -> Result<(Ipv6Addr, Ipv6Addr), Error> {
+    let (addr, prefix) = match cidr.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (cidr, None),
+    };
+    let addr: IpAddr = addr.parse()?;
+    let max_prefix: u32 = if addr.is_ipv4() { 32 } else { 128 };
+    let prefix: u32 = match prefix {
+        Some(prefix) => prefix.parse()?,
+        None => max_prefix,
+    };
+    if prefix > max_prefix {
+        return Err(Error::new(ErrorCode::QueryParse, format!("invalid CIDR prefix length for {addr}: {prefix}")));
+    }
+    let prefix = prefix + (128 - max_prefix);
+    let mask = if prefix == 0 { 0 } else { u128::MAX << (128 - prefix) };
+    let addr = u128::from(addr.into_ipv6_addr());
+    Ok((Ipv6Addr::from(addr & mask), Ipv6Addr::from(addr | !mask)))
+}
+
+/// Returns a promise rejected with `err`, so that an async method fails the
+/// same way whether its arguments or its work are at fault.
+fn rejected<'cx>(cx: &mut Cx<'cx>, err: Handle<'cx, JsValue>) -> JsResult<'cx, JsPromise> {
+    let (deferred, promise) = cx.promise();
+    deferred.reject(cx, err);
+    Ok(promise)
+}
+
+/// Reads a `Query` argument, throwing a coded error for any other value.
+fn query_from_js<'cx>(cx: &mut Cx<'cx>, value: Handle<'cx, JsValue>) -> NeonResult<Query> {
+    match Query::try_from_js(cx, value)? {
+        Ok(query) => Ok(query),
+        Err(_) => Error::type_error(ErrorCode::InvalidArgument, "expected a Query").throw(cx),
+    }
+}
+
+/// Reads an options argument, which takes its default when it's omitted.
+fn options_from_js<'cx, T>(cx: &mut Cx<'cx>, options: Option<Handle<'cx, JsValue>>) -> NeonResult<T>
+where
+    T: for<'de> Deserialize<'de> + Default,
+{
+    let Some(options) = options else {
+        return Ok(T::default());
+    };
+    match Json::<T>::try_from_js(cx, options)? {
+        Ok(Json(options)) => Ok(options),
+        Err(err) => Error::type_error(ErrorCode::InvalidArgument, format!("invalid options: {err}")).throw(cx),
+    }
+}
+
+/// Reads the `gt`/`gte` and `lt`/`lte` properties of a range query's bounds
+/// object, converting each bound with `parse`.
+fn range_bounds<'cx, T>(
+    cx: &mut Cx<'cx>,
+    bounds: Handle<'cx, JsObject>,
+    parse: impl Fn(&mut Cx<'cx>, Handle<'cx, JsValue>) -> NeonResult<T>,
+) -> NeonResult<(Bound<T>, Bound<T>)> {
+    let lower = range_bound(cx, bounds, "gt", "gte", &parse)?;
+    let upper = range_bound(cx, bounds, "lt", "lte", &parse)?;
+    Ok((lower, upper))
+}
+
+fn range_bound<'cx, T>(
+    cx: &mut Cx<'cx>,
+    bounds: Handle<'cx, JsObject>,
+    exclusive: &str,
+    inclusive: &str,
+    parse: &impl Fn(&mut Cx<'cx>, Handle<'cx, JsValue>) -> NeonResult<T>,
+) -> NeonResult<Bound<T>> {
+    let excluded: Option<Handle<JsValue>> = bounds.get_opt(cx, exclusive)?;
+    let included: Option<Handle<JsValue>> = bounds.get_opt(cx, inclusive)?;
+    match bound(excluded, included, exclusive, inclusive) {
+        Ok(Bound::Excluded(value)) => Ok(Bound::Excluded(parse(cx, value)?)),
+        Ok(Bound::Included(value)) => Ok(Bound::Included(parse(cx, value)?)),
+        Ok(Bound::Unbounded) => Ok(Bound::Unbounded),
+        Err(err) => err.throw(cx),
+    }
+}
+
+/// Combines the exclusive and inclusive forms of one end of a range, at most
+/// one of which may be given.
+fn bound<T>(excluded: Option<T>, included: Option<T>, exclusive: &str, inclusive: &str) -> Result<Bound<T>, Error> {
+    match (excluded, included) {
+        (Some(_), Some(_)) => Err(Error::new(
+            ErrorCode::InvalidArgument,
+            format!("range bounds cannot specify both '{exclusive}' and '{inclusive}'"),
+        )),
+        (Some(value), None) => Ok(Bound::Excluded(value)),
+        (None, Some(value)) => Ok(Bound::Included(value)),
+        (None, None) => Ok(Bound::Unbounded),
+    }
+}
+
+/// Reads range bounds as values of the field's type.
+fn typed_range_bounds<'cx, T>(
+    cx: &mut Cx<'cx>,
+    name: &str,
+    field_type: &FieldType,
+    bounds: Handle<'cx, JsObject>,
+    extract: impl Fn(OwnedValue) -> Option<T>,
+) -> NeonResult<(Bound<T>, Bound<T>)> {
+    range_bounds(cx, bounds, |cx, value| {
+        match extract(doc::value_from_js(cx, name, field_type, value)?) {
+            Some(value) => Ok(value),
+            None => Error::type_error(ErrorCode::SchemaMismatch, format!(
+                "field '{name}': range bound is not a {} value",
+                field_type.value_type().name(),
+            )).throw(cx),
+        }
+    })
+}
+
+/// Builds a text term for a path within a JSON field.
+fn json_text_term(field: Field, path: &str, options: &JsonObjectOptions, text: &str) -> Term {
+    let mut term = Term::with_capacity(100);
+    let mut writer = JsonTermWriter::from_field_and_json_path(field, path, options.is_expand_dots_enabled(), &mut term);
+    writer.set_str(text);
+    writer.term().clone()
+}
+
+/// Builds a number, date or boolean term for a path within a JSON field if the
+/// text reads as one, the same way Tantivy's query parser does.
+fn json_fast_value_term(field: Field, path: &str, options: &JsonObjectOptions, text: &str) -> Option<Term> {
+    let mut term = Term::with_capacity(100);
+    let mut writer = JsonTermWriter::from_field_and_json_path(field, path, options.is_expand_dots_enabled(), &mut term);
+    convert_to_fast_value_and_get_term(&mut writer, text)
+}
+
+/// Reads a term for a path within a JSON field as text, which is then typed
+/// the same way the text of a query string is.
+fn json_term_text<'cx>(cx: &mut Cx<'cx>, name: &str, value: Handle<'cx, JsValue>) -> NeonResult<String> {
+    if let Ok(text) = value.downcast::<JsString, _>(cx) {
+        Ok(text.value(cx))
+    } else if let Ok(n) = value.downcast::<JsNumber, _>(cx) {
+        Ok(n.value(cx).to_string())
+    } else if let Ok(b) = value.downcast::<JsBoolean, _>(cx) {
+        Ok(b.value(cx).to_string())
+    } else {
+        Error::type_error(ErrorCode::InvalidArgument, format!(
+            "field '{name}': terms on JSON paths must be strings, numbers or booleans"
+        )).throw(cx)
+    }
+}
+
+/// Builds a term for a path within a JSON field, inferring the type of the
+/// value from its text.
+fn json_value_term(field: Field, path: &str, options: &JsonObjectOptions, text: &str) -> Term {
+    json_fast_value_term(field, path, options, text)
+        .unwrap_or_else(|| json_text_term(field, path, options, text))
+}
+
+/// Reads range bounds as terms at a path within a JSON field. Since a JSON
+/// field's term dictionary interleaves the values of every path and type, an
+/// open end of the range is closed off at the values of the same path and
+/// type as the other end.
+fn json_range_bounds<'cx>(
+    cx: &mut Cx<'cx>,
+    name: &str,
+    field: Field,
+    path: &str,
+    options: &JsonObjectOptions,
+    bounds: Handle<'cx, JsObject>,
+) -> NeonResult<(Bound<Term>, Bound<Term>)> {
+    let (lower, upper) = range_bounds(cx, bounds, |cx, value| {
+        let Json(json) = Json::<serde_json::Value>::from_js(cx, value)?;
+        let text = match json {
+            serde_json::Value::String(text) => text,
+            serde_json::Value::Number(n) => n.to_string(),
+            serde_json::Value::Bool(b) => b.to_string(),
+            _ => {
+                return Error::type_error(ErrorCode::InvalidArgument, format!(
+                    "field '{name}': range bound must be a string, number, boolean or date"
+                )).throw(cx);
+            }
+        };
+        Ok(json_value_term(field, path, options, &text))
+    })?;
+    let json_type = |bound: &Bound<Term>| match bound {
+        Bound::Included(term) | Bound::Excluded(term) => term.value().json_path_type(),
+        Bound::Unbounded => None,
+    };
+    let typ = match (json_type(&lower), json_type(&upper)) {
+        (Some(lower), Some(upper)) if lower != upper => {
+            return Error::type_error(ErrorCode::InvalidArgument, format!(
+                "field '{name}': range bounds have different types ({} and {})",
+                lower.name(),
+                upper.name(),
+            )).throw(cx);
+        }
+        (Some(typ), _) | (None, Some(typ)) => typ,
+        (None, None) => {
+            let message = format!("field '{name}': range queries on JSON paths need at least one bound");
+            return Error::new(ErrorCode::InvalidArgument, message).throw(cx);
+        }
+    };
+    let mut prefix = Term::with_capacity(100);
+    let mut writer = JsonTermWriter::from_field_and_json_path(field, path, options.is_expand_dots_enabled(), &mut prefix);
+    writer.close_path_and_set_type(typ);
+    let prefix = writer.term().clone();
+    let lower = match lower {
+        Bound::Unbounded => Bound::Included(prefix.clone()),
+        bound => bound,
+    };
+    let upper = match upper {
+        // No encoded value (UTF-8 text or a fixed-width 8-byte number) can sort after this.
+        Bound::Unbounded => {
+            let mut end = prefix;
+            end.append_bytes(&[0xff; 9]);
+            Bound::Included(end)
+        }
+        bound => bound,
+    };
+    Ok((lower, upper))
+}
+
+struct SearchResult {
+    score: Score,
+    doc: doc::Document,
+    explanation: Explanation,
+}
+
+impl SearchResult {
+    fn into_js<'cx>(self, cx: &mut Cx<'cx>) -> JsResult<'cx, JsArray> {
+        let result = JsArray::new(cx, 3);
+        let score = cx.number(self.score);
+        let doc = self.doc.into_js(cx)?;
+        let explanation = Json(self.explanation).try_into_js(cx)?;
+        result.set(cx, 0, score)?;
+        result.set(cx, 1, doc)?;
+        result.set(cx, 2, explanation)?;
+        Ok(result)
+    }
+}
+
+struct SearchResults(Vec<SearchResult>);
+
+impl SearchResults {
+    fn into_js<'cx>(self, cx: &mut Cx<'cx>) -> JsResult<'cx, JsArray> {
+        let results = JsArray::new(cx, self.0.len());
+        for (i, result) in self.0.into_iter().enumerate() {
+            let result = result.into_js(cx)?;
+            results.set(cx, i as u32, result)?;
+        }
+        Ok(results)
+    }
+}
+
+struct FacetSearchResults {
+    hits: SearchResults,
+    facets: Vec<(String, Vec<(String, u64)>)>,
+}
+
+impl FacetSearchResults {
+    fn into_js<'cx>(self, cx: &mut Cx<'cx>) -> JsResult<'cx, JsObject> {
+        let result = cx.empty_object();
+        let hits = self.hits.into_js(cx)?;
+        result.set(cx, "hits", hits)?;
+        let facets = cx.empty_object();
+        for (prefix, children) in self.facets {
+            let counts = JsArray::new(cx, children.len());
+            for (i, (facet, count)) in children.into_iter().enumerate() {
+                let pair = JsArray::new(cx, 2);
+                let facet = cx.string(facet);
+                let count = cx.number(count as f64);
+                pair.set(cx, 0, facet)?;
+                pair.set(cx, 1, count)?;
+                counts.set(cx, i as u32, pair)?;
+            }
+            facets.set(cx, prefix.as_str(), counts)?;
+        }
+        result.set(cx, "facets", facets)?;
+        Ok(result)
     }
 }
 
+/// The opstamp and payload of the last commit of an index.
+struct CommitMeta {
+    opstamp: u64,
+    payload: Option<String>,
+}
+
+impl CommitMeta {
+    fn into_js<'cx>(self, cx: &mut Cx<'cx>) -> JsResult<'cx, JsObject> {
+        let object = cx.empty_object();
+        let opstamp = JsBigInt::from_u64(cx, self.opstamp);
+        object.set(cx, "opstamp", opstamp)?;
+        let payload = match self.payload {
+            Some(payload) => cx.string(payload).upcast::<JsValue>(),
+            None => cx.null().upcast(),
+        };
+        object.set(cx, "payload", payload)?;
+        Ok(object)
+    }
+}
+
+/// The outcome of adding a batch of documents, some of which may have failed
+/// to parse without preventing the rest from being added.
+struct IngestSummary {
+    added: u64,
+    opstamp: Option<u64>,
+    // Failures are identified by array `index` or NDJSON `line`.
+    position: &'static str,
+    errors: Vec<(u64, String)>,
+}
+
+impl IngestSummary {
+    fn new(position: &'static str) -> Self {
+        Self {
+            added: 0,
+            opstamp: None,
+            position,
+            errors: vec![],
+        }
+    }
+
+    fn add(&mut self, writer: &IndexWriter, document: TantivyDocument) -> Result<(), Error> {
+        let opstamp = writer.add_document(document)?;
+        self.added += 1;
+        self.opstamp = Some(opstamp);
+        Ok(())
+    }
+
+    fn into_js<'cx>(self, cx: &mut Cx<'cx>) -> JsResult<'cx, JsObject> {
+        let object = cx.empty_object();
+        let added = cx.number(self.added as f64);
+        object.set(cx, "added", added)?;
+        let opstamp = match self.opstamp {
+            Some(opstamp) => JsBigInt::from_u64(cx, opstamp).upcast::<JsValue>(),
+            None => cx.null().upcast(),
+        };
+        object.set(cx, "opstamp", opstamp)?;
+        let errors = JsArray::new(cx, self.errors.len());
+        for (i, (position, message)) in self.errors.into_iter().enumerate() {
+            let error = cx.empty_object();
+            let position = cx.number(position as f64);
+            error.set(cx, self.position, position)?;
+            let message = cx.string(message);
+            error.set(cx, "message", message)?;
+            errors.set(cx, i as u32, error)?;
+        }
+        object.set(cx, "errors", errors)?;
+        Ok(object)
+    }
+}
+
+/// Parses newline-delimited JSON documents and adds them to the index,
+/// collecting the errors of any lines that fail to parse.
+fn ingest_ndjson(index: &OpenIndex, source: impl BufRead) -> Result<IngestSummary, Error> {
+    let schema = index.schema()?;
+    index.with_writer(|writer| {
+        let mut summary = IngestSummary::new("line");
+        for (i, line) in source.lines().enumerate() {
+            let line_number = i as u64 + 1;
+            let line = match line {
+                Ok(line) => line,
+                Err(err) if err.kind() == std::io::ErrorKind::InvalidData => {
+                    summary.errors.push((line_number, err.to_string()));
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match doc::from_json(&schema, &line) {
+                Ok(document) => summary.add(writer, document)?,
+                Err(err) => summary.errors.push((line_number, err.to_string())),
+            }
+        }
+        Ok(summary)
+    })
+}
+
 #[derive(Clone)]
 struct TextAnalyzer {
     analyzer: RefCell<t::TextAnalyzer>,
@@ -502,14 +2089,21 @@ struct TextAnalyzer {
 impl TextAnalyzer {
     fn new(
         filters: Option<Json<TextAnalyzerFilters>>,
-    ) -> Result<Self, Error> {
-        // TODO: need a way to build off something other than a simple tokenizer
-        let builder = t::TextAnalyzer::builder(SimpleTokenizer::default());
-        let Json(filters) = filters.unwrap_or(Json(TextAnalyzerFilters::default()));
-        let analyzer = filters.apply(builder);
-        Ok(Self {
-            analyzer: RefCell::new(analyzer),
-        })
+    ) -> Result<Self, impl Throwable> {
+        coded(Box::new(|| {
+            // TODO: need a way to build off something other than a simple tokenizer
+            let builder = t::TextAnalyzer::builder(SimpleTokenizer::default());
+            let Json(filters) = filters.unwrap_or(Json(TextAnalyzerFilters::default()));
+            if let Some(language) = filters.filter_stop_words {
+                if StopWordFilter::new(language.into()).is_none() {
+                    return Err(format!("no stop words are available for {language:?}").into());
+                }
+            }
+            let analyzer = filters.apply(builder);
+            Ok(Self {
+                analyzer: RefCell::new(analyzer),
+            })
+        }))
     }
 
     fn tokenize(&mut self, text: String) -> Json<Vec<Token>> {
@@ -537,78 +2131,547 @@ struct Index {
 #[neon::export(class)]
 impl Index {
     fn new(
-        path: String,
-        schema: Schema,
+        path: Option<String>,
+        schema: Option<Schema>,
         Json(options): Json<Option<IndexOptions>>,
-    ) -> Result<Self, Error> {
-        let dir_path = PathBuf::from(path);
-        let dir = tantivy::directory::MmapDirectory::open(dir_path)?;
-        let index = t::Index::create(dir, schema.schema.borrow().clone(), IndexSettings::default())?;
-        let options = options.unwrap_or_default();
-        let reader = Mutex::new(
-            index
-                .reader_builder()
-                .reload_policy(options.reload_on.into())
-                .try_into()?
-        );
-        let heap_size: u53 = options.heap_size.project()?;
-        let heap_size: u64 = heap_size.into();
-        let heap_size: usize = heap_size.try_into()?;
-        let writer = Mutex::new(index.writer(heap_size)?);
-        Ok(Self {
-            index: Arc::new(OpenIndex { index, writer, reader }),
-        })
+    ) -> Result<Self, impl Throwable> {
+        coded(Box::new(|| {
+            let options = options.unwrap_or_default();
+            // Without a path, the index lives in memory and is discarded along with it.
+            let dir: Box<dyn Directory> = match path {
+                Some(_) if options.in_memory => {
+                    return Err("an in-memory index cannot have a path".into());
+                }
+                Some(path) => Box::new(MmapDirectory::open(PathBuf::from(path))?),
+                None => Box::new(RamDirectory::create()),
+            };
+            let mut key = schema.as_ref().and_then(|schema| schema.key.clone());
+            let schema = schema.map(|schema| schema.schema.into_inner());
+            let exists = t::Index::exists(&*dir)?;
+            let index = match (options.mode, schema) {
+                (OpenMode::Create, Some(schema)) => create_index(dir, schema, &key)?,
+                (OpenMode::OpenOrCreate, Some(schema)) if !exists => create_index(dir, schema, &key)?,
+                (OpenMode::Create | OpenMode::OpenOrCreate, None) => {
+                    return Err("a schema is required to create an index".into());
+                }
+                (OpenMode::Open | OpenMode::OpenOrCreate | OpenMode::ReadOnly, schema) => {
+                    if !exists {
+                        return Err(Error::new(ErrorCode::IndexNotFound, "no index exists in the directory"));
+                    }
+                    let stored = read_schema_options(&*dir)?;
+                    let index = t::Index::open(dir)?;
+                    // A schema supplied when opening an index must match the one it was created with.
+                    if schema.is_some_and(|schema| schema != index.schema())
+                        || key.is_some() && stored.key.is_some() && key != stored.key
+                    {
+                        let message = "schema does not match the schema of the existing index";
+                        return Err(Error::new(ErrorCode::SchemaMismatch, message));
+                    }
+                    key = stored.key.or(key);
+                    index
+                }
+            };
+            let reader = Mutex::new(Some(
+                index
+                    .reader_builder()
+                    .reload_policy(options.reload_on.into())
+                    .try_into()?
+            ));
+            let writer_options = match options.mode {
+                OpenMode::ReadOnly => None,
+                _ => Some(WriterOptions::new(&options)?),
+            };
+            let writer = match &writer_options {
+                Some(writer_options) => Some(writer_options.create(&index)?),
+                None => None,
+            };
+            Ok(Self {
+                index: Arc::new(OpenIndex {
+                    index: Mutex::new(Some(index)),
+                    writer: Mutex::new(writer),
+                    writer_options,
+                    reader,
+                    searchers: Mutex::new(vec![]),
+                    key,
+                    preparing: AtomicBool::new(false),
+                    closed: AtomicBool::new(false),
+                }),
+            })
+        }))
     }
 
     #[neon(task)]
-    fn commit(self) -> Result<(), Error> {
+    fn commit(self) -> Result<(), impl Throwable> {
         self.commit_sync()
     }
 
-    fn commit_sync(&self) -> Result<(), Error> {
-        self.index.writer.lock().map_err(|_| "mutex poisoned")?.commit()?;
-        Ok(())
+    /// Closes the index after waiting for pending merges to finish. Once
+    /// closed, all methods of the index and its searchers throw.
+    #[neon(task)]
+    fn close(self) -> Result<(), impl Throwable> {
+        coded(Box::new(|| self.index.close(true)))
     }
 
+    /// Closes the index without waiting for pending merges, which are
+    /// cancelled.
+    fn close_sync(&self) -> Result<(), impl Throwable> {
+        coded(Box::new(|| self.index.close(false)))
+    }
+
+    fn commit_sync(&self) -> Result<(), impl Throwable> {
+        coded(Box::new(|| {
+            self.index.with_writer(|writer| Ok(writer.commit()?))?;
+            Ok(())
+        }))
+    }
+
+    /// Discards all operations since the last commit, returning the opstamp
+    /// of that commit.
     #[neon(task)]
-    fn reload(self) -> Result<(), Error> {
+    fn rollback(self) -> impl for<'cx> TryIntoJs<'cx> {
+        let stamp = self.index.rollback();
+        extract::with(move |cx| match stamp {
+            Ok(stamp) => Ok(JsBigInt::from_u64(cx, stamp)),
+            Err(err) => err.throw(cx),
+        })
+    }
+
+    fn rollback_sync<'cx>(&self, cx: &mut FunctionContext<'cx>) -> JsResult<'cx, JsBigInt> {
+        match self.index.rollback() {
+            Ok(stamp) => Ok(JsBigInt::from_u64(cx, stamp)),
+            Err(err) => err.throw(cx),
+        }
+    }
+
+    /// Lists the IDs of the committed segments of the index.
+    fn segment_ids(&self) -> Result<Json<Vec<String>>, impl Throwable> {
+        coded(Box::new(|| {
+            let segment_ids = self.index.index()?.searchable_segment_ids()?;
+            Ok(Json(segment_ids.iter().map(SegmentId::uuid_string).collect()))
+        }))
+    }
+
+    /// Merges the given segments into one, returning the ID of the new
+    /// segment, or `null` if the merged segments had no documents left.
+    #[neon(task)]
+    fn merge(self, segment_ids: Json<Vec<String>>) -> Result<Json<Option<String>>, impl Throwable> {
+        self.merge_sync(segment_ids)
+    }
+
+    fn merge_sync(&self, Json(segment_ids): Json<Vec<String>>) -> Result<Json<Option<String>>, impl Throwable> {
+        coded(Box::new(move || {
+            let segment_ids = segment_ids
+                .iter()
+                .map(|id| {
+                    SegmentId::from_uuid_string(id)
+                        .map_err(|err| Error::from(format!("invalid segment ID '{id}': {err}")))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            // Only scheduling the merge requires the writer.
+            let merge = self.index.with_writer(|writer| Ok(writer.merge(&segment_ids)))?;
+            let segment = merge.wait()?;
+            Ok(Json(segment.map(|segment| segment.id().uuid_string())))
+        }))
+    }
+
+    /// Waits for all merges to finish, discarding any operations that haven't
+    /// been committed.
+    #[neon(task)]
+    fn wait_merging_threads(self) -> Result<(), impl Throwable> {
+        self.wait_merging_threads_sync()
+    }
+
+    fn wait_merging_threads_sync(&self) -> Result<(), impl Throwable> {
+        coded(Box::new(|| self.index.wait_merging_threads()))
+    }
+
+    /// Deletes files that are no longer used by the index, such as the
+    /// files of merged segments.
+    #[neon(task)]
+    fn garbage_collect_files(self) -> Result<Json<GarbageCollection>, impl Throwable> {
+        self.garbage_collect_files_sync()
+    }
+
+    fn garbage_collect_files_sync(&self) -> Result<Json<GarbageCollection>, impl Throwable> {
+        coded(Box::new(|| {
+            let gc = self.index.with_writer(|writer| Ok(writer.garbage_collect_files()))?;
+            let result = gc.wait()?;
+            Ok(Json(GarbageCollection {
+                deleted_files: result.deleted_files,
+                failed_to_delete_files: result.failed_to_delete_files,
+            }))
+        }))
+    }
+
+    /// Starts a two-phase commit. Until the returned commit is committed or
+    /// aborted, all other writes to the index fail.
+    fn prepare_commit(&self) -> Result<PreparedCommit, impl Throwable> {
+        coded(Box::new(|| {
+            PreparedCommit::start(self.index.clone())
+        }))
+    }
+
+    fn last_commit<'cx>(&self, cx: &mut FunctionContext<'cx>) -> JsResult<'cx, JsObject> {
+        let metas = match self.index.index().and_then(|index| Ok(index.load_metas()?)) {
+            Ok(metas) => metas,
+            Err(err) => return err.throw(cx),
+        };
+        CommitMeta {
+            opstamp: metas.opstamp,
+            payload: metas.payload,
+        }
+        .into_js(cx)
+    }
+
+    #[neon(task)]
+    fn reload(self) -> Result<(), impl Throwable> {
         self.reload_sync()
     }
 
-    fn reload_sync(&self) -> Result<(), Error> {
-        self.index.reader.lock().map_err(|_| "mutex poisoned")?.reload()?;
-        Ok(())
+    fn reload_sync(&self) -> Result<(), impl Throwable> {
+        coded(Box::new(|| {
+            self.index.reader()?.reload()?;
+            Ok(())
+        }))
     }
 
     fn add_document<'cx>(
         &self,
         cx: &mut FunctionContext<'cx>,
-        Json(document): Json<serde_json::Map<String, serde_json::Value>>,
+        document: Handle<'cx, JsObject>,
     ) -> JsResult<'cx, JsBigInt> {
-        let document = match TantivyDocument::from_json_object(&self.index.index.schema(), document) {
-            Ok(doc) => doc,
-            Err(err) => {
-                return cx.throw_error(format!("failed to parse document: {}", err));
+        let schema = match self.index.schema() {
+            Ok(schema) => schema,
+            Err(err) => return err.throw(cx),
+        };
+        let document = doc::from_js(cx, &schema, document)?;
+        let stamp = match self.index.with_writer(|writer| Ok(writer.add_document(document)?)) {
+            Ok(stamp) => stamp,
+            Err(err) => return err.throw(cx),
+        };
+        Ok(JsBigInt::from_u64(cx, stamp))
+    }
+
+    /// Converts a batch of documents, then adds them to the index on a worker
+    /// thread. Documents that fail to convert are reported without
+    /// preventing the rest from being added.
+    fn add_documents<'cx>(
+        &self,
+        cx: &mut FunctionContext<'cx>,
+        documents: Handle<'cx, JsArray>,
+    ) -> JsResult<'cx, JsPromise> {
+        let schema = match self.index.schema() {
+            Ok(schema) => schema,
+            Err(err) => return err.throw(cx),
+        };
+        let documents = documents.to_vec(cx)?;
+        let mut summary = IngestSummary::new("index");
+        let mut converted = Vec::with_capacity(documents.len());
+        for (i, document) in documents.into_iter().enumerate() {
+            let result = cx.try_catch(|cx| {
+                let document = document.downcast_or_throw::<JsObject, _>(cx)?;
+                doc::from_js(cx, &schema, document)
+            });
+            match result {
+                Ok(document) => converted.push(document),
+                Err(err) => {
+                    let message = err.to_string(cx)?.value(cx);
+                    summary.errors.push((i as u64, message));
+                }
             }
+        }
+        let index = self.index.clone();
+        let promise = cx
+            .task(move || {
+                index.with_writer(|writer| {
+                    for document in converted {
+                        summary.add(writer, document)?;
+                    }
+                    Ok(summary)
+                })
+            })
+            .promise(|mut cx, result: Result<IngestSummary, Error>| match result {
+                Ok(summary) => summary.into_js(&mut cx),
+                Err(err) => err.throw(&mut cx),
+            });
+        Ok(promise)
+    }
+
+    /// Adds the documents of a newline-delimited JSON file or buffer on a
+    /// worker thread.
+    fn ingest_ndjson<'cx>(
+        &self,
+        cx: &mut FunctionContext<'cx>,
+        source: Handle<'cx, JsValue>,
+    ) -> JsResult<'cx, JsPromise> {
+        let source: Box<dyn BufRead + Send> = if let Ok(path) = source.downcast::<JsString, _>(cx) {
+            let path = path.value(cx);
+            match File::open(&path) {
+                Ok(file) => Box::new(BufReader::new(file)),
+                Err(err) => return Error::new(ErrorCode::IoError, format!("{path}: {err}")).throw(cx),
+            }
+        } else if let Ok(buffer) = source.downcast::<JsTypedArray<u8>, _>(cx) {
+            Box::new(Cursor::new(buffer.as_slice(cx).to_vec()))
+        } else {
+            return Error::type_error(ErrorCode::InvalidArgument, "expected a path or a buffer").throw(cx);
+        };
+        let index = self.index.clone();
+        let promise = cx
+            .task(move || ingest_ndjson(&index, source))
+            .promise(|mut cx, result| match result {
+                Ok(summary) => summary.into_js(&mut cx),
+                Err(err) => err.throw(&mut cx),
+            });
+        Ok(promise)
+    }
+
+    fn delete_term<'cx>(
+        &self,
+        cx: &mut FunctionContext<'cx>,
+        field: String,
+        value: Handle<'cx, JsValue>,
+    ) -> JsResult<'cx, JsBigInt> {
+        let schema = match self.index.schema() {
+            Ok(schema) => schema,
+            Err(err) => return err.throw(cx),
+        };
+        let field_id = match schema.get_field(&field) {
+            Ok(field_id) => field_id,
+            Err(err) => return Error::from(err).throw(cx),
+        };
+        let field_type = schema.get_field_entry(field_id).field_type();
+        if !field_type.is_indexed() {
+            return Error::new(ErrorCode::SchemaMismatch, format!("field '{field}' is not indexed")).throw(cx);
+        }
+        // A tokenized field indexes the tokens of its text rather than the text
+        // itself, so a term built from the whole value would never match.
+        if let FieldType::Str(options) = field_type {
+            if options.get_indexing_options().is_some_and(|indexing| indexing.tokenizer() != "raw") {
+                return Error::type_error(ErrorCode::SchemaMismatch, format!(
+                    "field '{field}' is tokenized, so its values can't be deleted by term; \
+                     use a STRING field or deleteQuery instead"
+                )).throw(cx);
+            }
+        }
+        let value = doc::value_from_js(cx, &field, field_type, value)?;
+        let Some(term) = doc::term_from_value(field_id, &value) else {
+            let message = format!("field '{field}' does not support deleting by term");
+            return Error::type_error(ErrorCode::SchemaMismatch, message).throw(cx);
+        };
+        let stamp = match self.index.with_writer(|writer| Ok(writer.delete_term(term))) {
+            Ok(stamp) => stamp,
+            Err(err) => return err.throw(cx),
         };
-        let stamp = self.index.writer
-            .lock()
-            .map_err(|_| "mutex poisoned").unwrap()
-            .add_document(document).unwrap();
         Ok(JsBigInt::from_u64(cx, stamp))
     }
 
-    fn searcher(&self) -> Result<Searcher, Error> {
-        Searcher::new(self.index.clone())
+    /// Replaces any documents with the same key as the given document, so
+    /// that adding the same document more than once is idempotent. The key
+    /// field defaults to the key field of the schema.
+    fn upsert_document<'cx>(
+        &self,
+        cx: &mut FunctionContext<'cx>,
+        key: Option<String>,
+        document: Handle<'cx, JsObject>,
+    ) -> JsResult<'cx, JsBigInt> {
+        let schema = match self.index.schema() {
+            Ok(schema) => schema,
+            Err(err) => return err.throw(cx),
+        };
+        let Some(key_name) = key.or_else(|| self.index.key.clone()) else {
+            let message = "no key field given, and the schema has no key field";
+            return Error::new(ErrorCode::InvalidArgument, message).throw(cx);
+        };
+        let key = match key_field(&schema, &key_name) {
+            Ok(key) => key,
+            Err(err) => return err.throw(cx),
+        };
+        let document = doc::from_js(cx, &schema, document)?;
+        let values = document.get_all(key).cloned().collect::<Vec<_>>();
+        let [value] = values.as_slice() else {
+            let message = format!("document must have exactly one value for key field '{key_name}'");
+            return Error::new(ErrorCode::SchemaMismatch, message).throw(cx);
+        };
+        let Some(term) = doc::term_from_value(key, value) else {
+            let message = format!("invalid value for key field '{key_name}'");
+            return Error::type_error(ErrorCode::SchemaMismatch, message).throw(cx);
+        };
+        // Running both operations as one batch assigns them consecutive
+        // opstamps, so no commit can separate the delete from the add.
+        let operations = vec![UserOperation::Delete(term), UserOperation::Add(document)];
+        let stamp = match self.index.with_writer(|writer| Ok(writer.run(operations)?)) {
+            Ok(stamp) => stamp,
+            Err(err) => return err.throw(cx),
+        };
+        Ok(JsBigInt::from_u64(cx, stamp))
+    }
+
+    fn delete_query<'cx>(
+        &self,
+        cx: &mut FunctionContext<'cx>,
+        query: Query,
+    ) -> JsResult<'cx, JsBigInt> {
+        let query = (**query.query).box_clone();
+        let stamp = match self.index.with_writer(|writer| Ok(writer.delete_query(query)?)) {
+            Ok(stamp) => stamp,
+            Err(err) => return err.throw(cx),
+        };
+        Ok(JsBigInt::from_u64(cx, stamp))
+    }
+
+    fn schema(&self) -> Result<Schema, impl Throwable> {
+        coded(Box::new(|| Ok(Schema::from_tantivy(self.index.schema()?, self.index.key.clone()))))
+    }
+
+    fn searcher(&self) -> Result<Searcher, impl Throwable> {
+        coded(Box::new(|| self.index.searcher()))
     }
 
     fn register_tokenizer(
         &self,
         name: String,
         tokenizer: TextAnalyzer,
-    ) {
-        let manager = self.index.index.tokenizers();
-        manager.register(&name, tokenizer.analyzer.borrow().clone());
+    ) -> Result<(), impl Throwable> {
+        coded(Box::new(move || {
+            let index = self.index.index()?;
+            let analyzer = tokenizer.analyzer.borrow();
+            index.tokenizers().register(&name, analyzer.clone());
+            // Also make the tokenizer available as a `fastTokenizer` normalizer.
+            index.fast_field_tokenizer().register(&name, analyzer.clone());
+            Ok(())
+        }))
+    }
+}
+
+enum CommitDecision {
+    Commit(Option<String>),
+    Abort,
+}
+
+struct PendingCommit {
+    decision: Sender<CommitDecision>,
+    thread: JoinHandle<Result<u64, Error>>,
+}
+
+/// A commit whose segments have been flushed but which isn't yet visible.
+///
+/// Tantivy's `PreparedCommit` borrows the writer, so it can't outlive a
+/// single call from JS. Instead, it lives on a dedicated thread, which holds
+/// the writer lock until it's told whether to commit or abort.
+#[derive(Clone)]
+struct PreparedCommit {
+    opstamp: u64,
+    payload: RefCell<Option<String>>,
+    pending: Arc<Mutex<Option<PendingCommit>>>,
+}
+
+impl PreparedCommit {
+    /// Prepares a commit of the index. The JS constructor throws instead, since
+    /// a prepared commit can only be created by `Index.prepareCommit()`.
+    fn start(index: Arc<OpenIndex>) -> Result<Self, Error> {
+        if index.preparing.swap(true, Ordering::AcqRel) {
+            return Err(Error::new(ErrorCode::LockBusy, "a prepared commit is already pending"));
+        }
+        let (prepared_tx, prepared_rx) = mpsc::channel();
+        let (decision_tx, decision_rx) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            let result = index.with_locked_writer(|writer| {
+                let mut prepared = writer.prepare_commit()?;
+                let _ = prepared_tx.send(prepared.opstamp());
+                match decision_rx.recv() {
+                    Ok(CommitDecision::Commit(payload)) => {
+                        if let Some(payload) = payload {
+                            prepared.set_payload(&payload);
+                        }
+                        Ok(prepared.commit()?)
+                    }
+                    // A commit that's garbage collected without being resolved is aborted.
+                    Ok(CommitDecision::Abort) | Err(_) => Ok(prepared.abort()?),
+                }
+            });
+            index.preparing.store(false, Ordering::Release);
+            result
+        });
+        let pending = PendingCommit { decision: decision_tx, thread };
+        let opstamp = match prepared_rx.recv() {
+            Ok(opstamp) => opstamp,
+            // The thread failed to prepare the commit, so joining it reports why.
+            Err(_) => {
+                return match pending.thread.join() {
+                    Ok(Err(err)) => Err(err),
+                    _ => Err(Error::new(ErrorCode::Internal, "commit thread panicked")),
+                };
+            }
+        };
+        Ok(Self {
+            opstamp,
+            payload: RefCell::new(None),
+            pending: Arc::new(Mutex::new(Some(pending))),
+        })
+    }
+
+    fn resolve(&self, decision: CommitDecision) -> Result<u64, Error> {
+        let pending = self.pending
+            .lock()?
+            .take()
+            .ok_or("commit has already been resolved")?;
+        // If the thread has already exited, joining it reports why.
+        let _ = pending.decision.send(decision);
+        match pending.thread.join() {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(Error::new(ErrorCode::Internal, "commit thread panicked")),
+        }
+    }
+}
+
+#[neon::export(class)]
+impl PreparedCommit {
+    fn new() -> Result<Self, impl Throwable> {
+        coded(Box::new(|| {
+            Err(Error::type_error(ErrorCode::InvalidArgument, "use Index.prepareCommit() to prepare a commit"))
+        }))
+    }
+
+    fn opstamp<'cx>(&self, cx: &mut FunctionContext<'cx>) -> Handle<'cx, JsBigInt> {
+        JsBigInt::from_u64(cx, self.opstamp)
+    }
+
+    fn set_payload(&self, payload: String) {
+        *self.payload.borrow_mut() = Some(payload);
+    }
+
+    #[neon(task)]
+    fn commit(self) -> impl for<'cx> TryIntoJs<'cx> {
+        let payload = self.payload.borrow().clone();
+        let stamp = self.resolve(CommitDecision::Commit(payload));
+        extract::with(move |cx| match stamp {
+            Ok(stamp) => Ok(JsBigInt::from_u64(cx, stamp)),
+            Err(err) => err.throw(cx),
+        })
+    }
+
+    fn commit_sync<'cx>(&self, cx: &mut FunctionContext<'cx>) -> JsResult<'cx, JsBigInt> {
+        let payload = self.payload.borrow().clone();
+        match self.resolve(CommitDecision::Commit(payload)) {
+            Ok(stamp) => Ok(JsBigInt::from_u64(cx, stamp)),
+            Err(err) => err.throw(cx),
+        }
+    }
+
+    #[neon(task)]
+    fn abort(self) -> impl for<'cx> TryIntoJs<'cx> {
+        let stamp = self.resolve(CommitDecision::Abort);
+        extract::with(move |cx| match stamp {
+            Ok(stamp) => Ok(JsBigInt::from_u64(cx, stamp)),
+            Err(err) => err.throw(cx),
+        })
+    }
+
+    fn abort_sync<'cx>(&self, cx: &mut FunctionContext<'cx>) -> JsResult<'cx, JsBigInt> {
+        match self.resolve(CommitDecision::Abort) {
+            Ok(stamp) => Ok(JsBigInt::from_u64(cx, stamp)),
+            Err(err) => err.throw(cx),
+        }
     }
 }
 
@@ -627,14 +2690,132 @@ impl Query {
 }
 
 struct OpenIndex {
-    index: t::Index,
-    writer: Mutex<IndexWriter>,
-    reader: Mutex<IndexReader>,
+    // The index, its writer and its reader are absent once the index is closed.
+    index: Mutex<Option<t::Index>>,
+    writer: Mutex<Option<IndexWriter>>,
+    // Absent for read-only indexes.
+    writer_options: Option<WriterOptions>,
+    reader: Mutex<Option<IndexReader>>,
+    // The searchers to release when the index is closed.
+    searchers: Mutex<Vec<Weak<SearcherSlot>>>,
+    key: Option<String>,
+    // Set while a `PreparedCommit` holds the writer, so that other writes
+    // fail instead of blocking until the commit is resolved.
+    preparing: AtomicBool,
+    closed: AtomicBool,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl OpenIndex {
+    fn index(&self) -> Result<t::Index, Error> {
+        self.index.lock()?.clone().ok_or_else(index_closed)
+    }
+
+    fn schema(&self) -> Result<t::Schema, Error> {
+        Ok(self.index()?.schema())
+    }
+
+    fn reader(&self) -> Result<IndexReader, Error> {
+        self.reader.lock()?.clone().ok_or_else(index_closed)
+    }
+
+    /// Runs `f` with the index writer, failing instead of blocking while a
+    /// prepared commit holds it.
+    fn with_writer<T>(&self, f: impl FnOnce(&mut IndexWriter) -> Result<T, Error>) -> Result<T, Error> {
+        if self.preparing.load(Ordering::Acquire) {
+            return Err(Error::new(ErrorCode::LockBusy, "a prepared commit is pending"));
+        }
+        self.with_locked_writer(f)
+    }
+
+    fn with_locked_writer<T>(&self, f: impl FnOnce(&mut IndexWriter) -> Result<T, Error>) -> Result<T, Error> {
+        let Some(writer_options) = &self.writer_options else {
+            return Err(Error::new(ErrorCode::ReadOnly, "index is read-only"));
+        };
+        let mut writer = self.writer.lock()?;
+        // Checking while holding the lock ensures that a writer taken by
+        // `close` isn't recreated.
+        if self.closed.load(Ordering::Acquire) {
+            return Err(index_closed());
+        }
+        // The writer is missing if recreating it after waiting for merges
+        // failed, in which case it's recreated on its next use.
+        let writer = match &mut *writer {
+            Some(writer) => writer,
+            None => writer.insert(writer_options.create(&self.index()?)?),
+        };
+        f(writer)
+    }
+
+    /// Waits for all merges to finish. Waiting consumes the writer, along
+    /// with any operations that haven't been committed, so a new writer with
+    /// the same options replaces it.
+    fn wait_merging_threads(&self) -> Result<(), Error> {
+        if self.preparing.load(Ordering::Acquire) {
+            return Err(Error::new(ErrorCode::LockBusy, "a prepared commit is pending"));
+        }
+        let Some(writer_options) = &self.writer_options else {
+            return Err(Error::new(ErrorCode::ReadOnly, "index is read-only"));
+        };
+        let mut slot = self.writer.lock()?;
+        if self.closed.load(Ordering::Acquire) {
+            return Err(index_closed());
+        }
+        let Some(writer) = slot.take() else {
+            return Ok(());
+        };
+        let result = writer.wait_merging_threads();
+        *slot = Some(writer_options.create(&self.index()?)?);
+        Ok(result?)
+    }
+
+    fn searcher(&self) -> Result<Searcher, Error> {
+        let searcher: Arc<SearcherSlot> = Arc::new(Mutex::new(Some(Arc::new(self.reader()?.searcher()))));
+        let mut searchers = self.searchers.lock()?;
+        // Checking while holding the lock ensures that `close` releases
+        // every searcher it doesn't reject.
+        if self.closed.load(Ordering::Acquire) {
+            return Err(index_closed());
+        }
+        searchers.retain(|searcher| searcher.strong_count() > 0);
+        searchers.push(Arc::downgrade(&searcher));
+        Ok(Searcher { searcher })
+    }
+
+    /// Closes the index, dropping its writer to release the directory lock,
+    /// and its reader and searchers to unmap its files. Operations that
+    /// haven't been committed are discarded.
+    fn close(&self, wait_merging_threads: bool) -> Result<(), Error> {
+        if self.preparing.load(Ordering::Acquire) {
+            return Err(Error::new(ErrorCode::LockBusy, "a prepared commit is pending"));
+        }
+        // Closing an index more than once has no effect.
+        if self.closed.swap(true, Ordering::AcqRel) {
+            return Ok(());
+        }
+        let writer = self.writer.lock()?.take();
+        for searcher in self.searchers.lock()?.drain(..) {
+            if let Some(searcher) = searcher.upgrade() {
+                searcher.lock()?.take();
+            }
+        }
+        self.reader.lock()?.take();
+        self.index.lock()?.take();
+        if let (Some(writer), true) = (writer, wait_merging_threads) {
+            writer.wait_merging_threads()?;
+        }
+        Ok(())
+    }
+
+    fn rollback(&self) -> Result<u64, Error> {
+        self.with_writer(|writer| Ok(writer.rollback()?))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 enum ReloadOnPolicy {
+    /// Reload shortly after `meta.json` changes, including commits made by
+    /// another process, such as the writer of a `READ_ONLY` index.
     CommitWithDelay,
     Manual,
 }
@@ -648,6 +2829,16 @@ impl From<ReloadOnPolicy> for ReloadPolicy {
     }
 }
 
+fn index_closed() -> Error {
+    Error::new(ErrorCode::IndexClosed, "index is closed")
+}
+
+fn to_usize(n: f64) -> Result<usize, Error> {
+    let n: u53 = n.project()?;
+    let n: u64 = n.into();
+    Ok(n.try_into()?)
+}
+
 fn count_chars_until_offset(i: &mut CharIndices, byte_offset: usize) -> usize {
     let mut chars = 0;
     while i.offset() < byte_offset {
@@ -658,3 +2849,79 @@ fn count_chars_until_offset(i: &mut CharIndices, byte_offset: usize) -> usize {
     }
     chars
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numeric_options_applies_flags() {
+        let options = numeric_options::<NumericOptions>(&None);
+        assert!(!options.is_stored() && !options.is_indexed());
+        let options = numeric_options::<NumericOptions>(&Some(vec![NumericOption::STORED, NumericOption::INDEXED]));
+        assert!(options.is_stored() && options.is_indexed());
+        let options = numeric_options::<DateOptions>(&Some(vec![NumericOption::INDEXED]));
+        assert!(!options.is_stored() && options.is_indexed());
+        let options = numeric_options::<NumericOptions>(&Some(vec![NumericOption::FAST]));
+        assert!(options.is_fast() && !options.is_indexed());
+    }
+
+    #[test]
+    fn text_options_applies_flags() {
+        let options = text_options(tantivy::schema::STRING, &None, &None);
+        assert!(!options.is_stored() && !options.is_fast());
+        let flags = Some(vec![TextOption::STORED, TextOption::FAST]);
+        let options = text_options(tantivy::schema::TEXT, &flags, &Some("lower".to_string()));
+        assert!(options.is_stored() && options.is_fast());
+        assert_eq!(options.get_fast_field_tokenizer_name(), Some("lower"));
+    }
+
+    #[test]
+    fn parse_ip_range_single_address() {
+        let addr: Ipv6Addr = "::ffff:10.1.2.3".parse().unwrap();
+        assert_eq!(parse_ip_range("10.1.2.3").unwrap(), (addr, addr));
+        let addr: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        assert_eq!(parse_ip_range("2001:db8::1").unwrap(), (addr, addr));
+    }
+
+    #[test]
+    fn parse_ip_range_cidr() {
+        let (lower, upper) = parse_ip_range("10.0.0.0/8").unwrap();
+        assert_eq!(lower, "::ffff:10.0.0.0".parse::<Ipv6Addr>().unwrap());
+        assert_eq!(upper, "::ffff:10.255.255.255".parse::<Ipv6Addr>().unwrap());
+        let (lower, upper) = parse_ip_range("2001:db8::/32").unwrap();
+        assert_eq!(lower, "2001:db8::".parse::<Ipv6Addr>().unwrap());
+        assert_eq!(upper, "2001:db8:ffff:ffff:ffff:ffff:ffff:ffff".parse::<Ipv6Addr>().unwrap());
+        let (lower, upper) = parse_ip_range("0.0.0.0/0").unwrap();
+        assert_eq!(lower, "::ffff:0.0.0.0".parse::<Ipv6Addr>().unwrap());
+        assert_eq!(upper, "::ffff:255.255.255.255".parse::<Ipv6Addr>().unwrap());
+    }
+
+    #[test]
+    fn parse_ip_range_rejects_invalid_input() {
+        assert!(parse_ip_range("10.0.0.0/33").is_err());
+        assert!(parse_ip_range("::/129").is_err());
+        assert!(parse_ip_range("10.0.0.0/x").is_err());
+        assert!(parse_ip_range("not an address").is_err());
+    }
+
+    #[test]
+    fn bound_prefers_the_given_form() {
+        assert_eq!(bound(Some(1), None, "gt", "gte").unwrap(), Bound::Excluded(1));
+        assert_eq!(bound(None, Some(2), "lt", "lte").unwrap(), Bound::Included(2));
+        assert_eq!(bound::<i64>(None, None, "gt", "gte").unwrap(), Bound::Unbounded);
+    }
+
+    #[test]
+    fn bound_rejects_both_forms() {
+        let err = bound(Some(1), Some(2), "gt", "gte").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidArgument);
+        assert_eq!(err.to_string(), "range bounds cannot specify both 'gt' and 'gte'");
+    }
+
+    #[test]
+    fn date_precision_defaults_to_milliseconds() {
+        let precision: DateTimePrecision = DatePrecision::default().into();
+        assert_eq!(precision, DateTimePrecision::Milliseconds);
+    }
+}
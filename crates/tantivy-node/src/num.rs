@@ -13,6 +13,31 @@ impl u53 {
     }
 }
 
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+#[allow(non_camel_case_types)]
+pub struct i54(i64);
+
+impl i54 {
+    pub const MIN: Self = Self(-0x1fffffffffffff_i64);
+    pub const MAX: Self = Self(0x1fffffffffffff_i64);
+
+    const fn new(x: i64) -> i54 {
+        i54(x)
+    }
+}
+
+impl fmt::Display for i54 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Debug for i54 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
 impl fmt::Display for u53 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.0)
@@ -76,6 +101,30 @@ impl Project<u53> for f64 {
     }
 }
 
+impl From<i54> for f64 {
+    fn from(x: i54) -> f64 {
+        x.0 as f64
+    }
+}
+
+impl From<i54> for i64 {
+    fn from(x: i54) -> i64 {
+        x.0
+    }
+}
+
+impl Project<i54> for f64 {
+    fn project(self) -> Result<i54, ProjectionError> {
+        if self.trunc() != self {
+            return Err(ProjectionError(format!("{self} is not an integer")));
+        }
+        if self < i54::MIN.into() || self > i54::MAX.into() {
+            return Err(ProjectionError(format!("{self} is out of range for i54")));
+        }
+        Ok(i54::new(self as i64))
+    }
+}
+
 macro_rules! impl_project {
     ($t:ident) => {
         impl Project<$t> for f64 {
@@ -95,3 +144,41 @@ macro_rules! impl_project {
 impl_project!(u32);
 impl_project!(u16);
 impl_project!(u8);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn project_i54() {
+        let n: i54 = 0.0.project().unwrap();
+        assert_eq!(i64::from(n), 0);
+        let n: i54 = (-42.0).project().unwrap();
+        assert_eq!(i64::from(n), -42);
+        let n: i54 = 9007199254740991.0.project().unwrap();
+        assert_eq!(n, i54::MAX);
+        let n: i54 = (-9007199254740991.0).project().unwrap();
+        assert_eq!(n, i54::MIN);
+    }
+
+    #[test]
+    fn project_i54_rejects_non_integers() {
+        assert!(Project::<i54>::project(1.5).is_err());
+        assert!(Project::<i54>::project(f64::NAN).is_err());
+        assert!(Project::<i54>::project(f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn project_i54_rejects_out_of_range() {
+        assert!(Project::<i54>::project(9007199254740992.0).is_err());
+        assert!(Project::<i54>::project(-9007199254740992.0).is_err());
+    }
+
+    #[test]
+    fn project_u53() {
+        let n: u53 = 9007199254740991.0.project().unwrap();
+        assert_eq!(n, u53::MAX);
+        assert!(Project::<u53>::project(-1.0).is_err());
+        assert!(Project::<u53>::project(9007199254740992.0).is_err());
+    }
+}
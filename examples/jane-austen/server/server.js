@@ -81,12 +81,11 @@ function serve(index) {
       ? searcher.termQuery(terms[0], "text", "WITH_FREQS_AND_POSITIONS")
       : searcher.phraseQuery(terms, "text");
     searcher.search(queryObject, { top: 10 }).then(results => {
-      const items = results.map(([_score, docSrc, _explanation]) => {
+      const items = results.map(([_score, doc, _explanation]) => {
         // Since a TantivyDocument may contain duplicate fields,
-        // each field is represented in JSON as an array:
+        // each field is represented as an array:
         //
         // https://docs.rs/tantivy/0.22.0/tantivy/schema/document/struct.TantivyDocument.html
-        const doc = JSON.parse(docSrc);
         const matches = findMatches(doc.text[0], tokens);
         return {
           icon: `${doc.title[0].replaceAll(' ', '-').toLowerCase()}.jpg`,
@@ -1,6 +1,6 @@
 import * as fs from 'fs/promises';
 import { Index, Schema } from 'tantivy';
-import { getTestIndexPath } from 'utils';
+import { getIndexPath } from 'utils';
 
 const BOOKS = [
   'emma',
@@ -35,15 +35,44 @@ const PARAGRAPH_INDEX = {
   sourceDir: "by-paragraph",
 }
 
+// The payload of the commit that finishes a build. An index without it was
+// left behind by an interrupted build, so it's rebuilt from scratch.
+const BUILT = "built";
+
+// Opens the index built by a previous run, if there is one. Delete the cache
+// directory to rebuild it.
+async function openBuiltIndex(indexPath, options) {
+  let index;
+  try {
+    index = Index.open(indexPath, options);
+  } catch (err) {
+    if (err.code === 'INDEX_NOT_FOUND') {
+      return null;
+    }
+    throw err;
+  }
+  if (index.lastCommit().payload !== BUILT) {
+    await index.close();
+    return null;
+  }
+  return index;
+}
+
 export default async function buildIndex(tokenizer) {
+  const indexPath = getIndexPath(PARAGRAPH_INDEX.cacheDir);
+  const options = { heapSize: PARAGRAPH_INDEX.heapSize };
+
+  const built = await openBuiltIndex(indexPath, options);
+  if (built) {
+    built.registerTokenizer('jane_austen', tokenizer);
+    await built.reload();
+    return built;
+  }
+
+  await fs.rm(indexPath, { recursive: true, force: true });
+  await fs.mkdir(indexPath, { recursive: true });
   const schema = new Schema(PARAGRAPH_INDEX.schema);
-  const index = new Index(
-    getTestIndexPath(PARAGRAPH_INDEX.cacheDir),
-    schema,
-    {
-      heapSize: PARAGRAPH_INDEX.heapSize
-    }
-  );
+  const index = Index.openOrCreate(indexPath, schema, options);
 
   index.registerTokenizer('jane_austen', tokenizer);
 
@@ -53,11 +82,17 @@ export default async function buildIndex(tokenizer) {
     const docs = JSON.parse(await fs.readFile(`${import.meta.dirname}/${PARAGRAPH_INDEX.sourceDir}/${book}.json`, 'utf8'));
     for (const doc of docs) {
       doc.raw = doc.text;
-      await index.addDocument(doc);
+    }
+    const { errors } = await index.addDocuments(docs);
+    if (errors.length) {
+      throw new Error(`${book}: failed to add document ${errors[0].index}: ${errors[0].message}`);
     }
   }
 
-  await index.commit();
+  // Mark the build as finished in the same commit that adds the documents.
+  const prepared = index.prepareCommit();
+  prepared.setPayload(BUILT);
+  await prepared.commit();
   await index.reload();
 
   return index;
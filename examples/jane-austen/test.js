@@ -32,7 +32,7 @@ async function test() {
       console.error(paragraphs.searchTerms("text", "knight.*"));
       console.error("Searching paragraphs...");
       return await paragraphs.search("love", {
-        fields: ["text"],
+        defaultFields: ["text"],
         top: 10
       });
     }),
@@ -51,7 +51,7 @@ test()
 
     console.error("Paragraph search:");
     const paragraphsSummary = result.paragraphs.result.map(([score, doc, _explanation]) => {
-      return { score, doc: JSON.parse(doc) };
+      return { score, doc };
     });
     console.log(JSON.stringify(paragraphsSummary, 0, 2));
     console.error(`Search time: ${result.paragraphs.time}ms`);
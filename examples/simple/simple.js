@@ -33,7 +33,7 @@ async function test() {
   const searcher = index.searcher();
 
   return await searcher.search("fur", {
-    fields: ["title", "url"],
+    defaultFields: ["title", "url"],
     top: 10
   });
 }
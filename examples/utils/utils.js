@@ -12,6 +12,13 @@ export function getTestIndexPath(indexName = "test") {
   return indexPath;
 }
 
+// Unlike `getTestIndexPath`, keeps any index left by a previous run.
+export function getIndexPath(indexName) {
+  const indexPath = path.join(INDEX_PATH, indexName);
+  mkdirSync(indexPath, { recursive: true });
+  return indexPath;
+}
+
 export async function benchmark(thunk) {
   const start = performance.now();
   const result = await thunk();
@@ -6,18 +6,31 @@ declare module "./load.cjs" {
   export interface Query {}
 
   export class Schema {
-    constructor(fields: SchemaDescriptor);
+    constructor(fields: SchemaDescriptor, options?: SchemaOptions);
     fields(): SchemaDescriptor;
+    key(): string | null;
   }
 
   export interface Searcher {
-    termQuery(term: string, field: string, options?: IndexRecordOption): Query;
+    termQuery(term: TermValue, field: string, options?: IndexRecordOption): Query;
     phraseQuery(terms: string[], field: string): Query;
     fuzzyTermQuery(term: string, field: string, options?: FuzzyTermQueryOptions): Query;
     regexpQuery(pattern: string, field: string): Query;
     phrasePrefixQuery(terms: string[], field: string): Query;
-    searchSync(query: Query, options: SearchOptions): SearchResult[];
-    search(query: Query, options: SearchOptions): Promise<SearchResult[]>;
+    rangeQuery(field: string, bounds: RangeBounds): Query;
+    allQuery(): Query;
+    existsQuery(field: string): Query;
+    termSetQuery(field: string, values: TermValue[]): Query;
+    booleanQuery(clauses: BooleanClause[], options?: BooleanQueryOptions): Query;
+    boostQuery(query: Query, factor: number): Query;
+    constScoreQuery(query: Query, score: number): Query;
+    disjunctionMaxQuery(queries: Query[], tieBreaker?: number): Query;
+    parseQuery(text: string, options?: ParseQueryOptions): Query;
+    parseQueryLenient(text: string, options?: ParseQueryOptions): LenientQuery;
+    searchSync(query: Query | string, options?: SearchOptions): SearchResult[];
+    search(query: Query | string, options?: SearchOptions): Promise<SearchResult[]>;
+    facetSearchSync(query: Query, field: string, prefixes: string[], options?: SearchOptions): FacetSearchResult;
+    facetSearch(query: Query, field: string, prefixes: string[], options?: SearchOptions): Promise<FacetSearchResult>;
     searchTerms(field: string, pattern: string): string[];
   }
 
@@ -26,12 +39,44 @@ declare module "./load.cjs" {
     tokenize(text: string): Token[];
   }
 
+  export interface PreparedCommit {
+    opstamp(): bigint;
+    setPayload(payload: string): void;
+    commit(): Promise<bigint>;
+    commitSync(): bigint;
+    abort(): Promise<bigint>;
+    abortSync(): bigint;
+  }
+
   export class Index {
-    constructor(path: string, schema: Schema, options?: IndexOptions);
+    constructor(path: string | null, schema: Schema | null, options?: IndexOptions);
+    static open(path: string, options?: IndexOptions): Index;
+    static openOrCreate(path: string, schema: Schema, options?: IndexOptions): Index;
     schema(): Schema;
-    addDocument(doc: any): BigInt;
+    addDocument(doc: any): bigint;
+    addDocuments(docs: any[]): Promise<IngestSummary<'index'>>;
+    ingestNdjson(source: string | Uint8Array): Promise<IngestSummary<'line'>>;
+    upsertDocument(keyField: string | null, doc: any): bigint;
+    deleteTerm(field: string, value: any): bigint;
+    deleteQuery(query: Query): bigint;
     commit(): Promise<void>;
     commitSync(): void;
+    // Closing an index discards any operations that haven't been committed.
+    close(): Promise<void>;
+    closeSync(): void;
+    rollback(): Promise<bigint>;
+    rollbackSync(): bigint;
+    prepareCommit(): PreparedCommit;
+    segmentIds(): string[];
+    merge(segmentIds: string[]): Promise<string | null>;
+    mergeSync(segmentIds: string[]): string | null;
+    // Like closing, waiting for merges discards any operations that haven't
+    // been committed, so commit them first.
+    waitMergingThreads(): Promise<void>;
+    waitMergingThreadsSync(): void;
+    garbageCollectFiles(): Promise<GarbageCollection>;
+    garbageCollectFilesSync(): GarbageCollection;
+    lastCommit(): CommitMeta;
     reload(): Promise<void>;
     reloadSync(): void;
     searcher(): Searcher;
@@ -39,6 +84,23 @@ declare module "./load.cjs" {
   }
 }
 
+export type ErrorCode =
+  | 'SCHEMA_MISMATCH'
+  | 'FIELD_NOT_FOUND'
+  | 'LOCK_BUSY'
+  | 'READ_ONLY'
+  | 'INDEX_CLOSED'
+  | 'INDEX_NOT_FOUND'
+  | 'IO_ERROR'
+  | 'QUERY_PARSE'
+  | 'INVALID_ARGUMENT'
+  | 'INTERNAL';
+
+// The type of errors thrown by this library.
+export interface TantivyError extends Error {
+  code: ErrorCode,
+}
+
 export type Token = {
   byteOffsetFrom: number,
   byteOffsetTo: number,
@@ -86,16 +148,27 @@ export enum IndexRecordOption {
   WithFreqsAndPositions = "WITH_FREQS_AND_POSITIONS",
 }
 
+// Text fields are indexed by default with the "default" tokenizer,
+// WITH_FREQS_AND_POSITIONS, and fieldnorms, like Tantivy's TEXT option.
+// A field with a custom `tokenizer` defaults to the BASIC index option
+// instead. Set `indexed: false` for a stored-only field.
+//
+// With the FAST flag, `fastTokenizer` names the tokenizer used to
+// normalize values in the fast field (e.g. "raw" or "default").
 export type TextFieldDescriptor = {
   type: "text",
   flags?: TextOption[],
+  indexed?: boolean,
   index?: IndexRecordOption,
   tokenizer?: string,
+  fieldnorms?: boolean,
+  fastTokenizer?: string,
 };
 
 export type StringFieldDescriptor = {
   type: "string",
   flags?: TextOption[],
+  fastTokenizer?: string,
 };
 
 export type F64FieldDescriptor = {
@@ -103,34 +176,197 @@ export type F64FieldDescriptor = {
   flags?: NumericOption[],
 };
 
-// TODO: | I64FieldDescriptor
-// TODO: | U64FieldDescriptor
-// TODO: | DateFieldDescriptor
-// TODO: | BoolFieldDescriptor
-// TODO: | IpAddrFieldDescriptor
+export type I64FieldDescriptor = {
+  type: "i64",
+  flags?: NumericOption[],
+};
+
+export type U64FieldDescriptor = {
+  type: "u64",
+  flags?: NumericOption[],
+};
+
+export type DatePrecision = 'SECONDS' | 'MILLISECONDS' | 'MICROSECONDS' | 'NANOSECONDS';
+
+export type DateFieldDescriptor = {
+  type: "date",
+  flags?: NumericOption[],
+  // Defaults to 'MILLISECONDS', the precision of JS dates.
+  precision?: DatePrecision,
+};
+
+export type BoolFieldDescriptor = {
+  type: "bool",
+  flags?: NumericOption[],
+};
+
+// IPv4 addresses are stored as IPv4-mapped IPv6 addresses.
+export type IpAddrFieldDescriptor = {
+  type: "ipAddr",
+  flags?: NumericOption[],
+};
+
+// Bytes values may be given as Buffers, Uint8Arrays, or base64 strings.
+export type BytesValue = Buffer | Uint8Array | string;
+
+export type BytesFieldDescriptor = {
+  type: "bytes",
+  flags?: NumericOption[],
+};
+
+// Nested values of a JSON field can be addressed in term and range
+// queries with a path such as "attrs.color".
+export type JsonFieldDescriptor = {
+  type: "json",
+  flags?: JsonOption[],
+  // Defaults to true. A field that isn't indexed can't be queried, but can
+  // still be stored or used as a fast field.
+  indexed?: boolean,
+  tokenizer?: string,
+  index?: IndexRecordOption,
+  fastTokenizer?: string,
+  expandDots?: boolean,
+};
+
+// Facet values are paths such as "/book/volume/chapter".
+export type FacetFieldDescriptor = {
+  type: "facet",
+  flags?: FacetOption[],
+};
+
 export type FieldDescriptor =
   TextFieldDescriptor
   | StringFieldDescriptor
-  | F64FieldDescriptor;
+  | F64FieldDescriptor
+  | I64FieldDescriptor
+  | U64FieldDescriptor
+  | DateFieldDescriptor
+  | BoolFieldDescriptor
+  | IpAddrFieldDescriptor
+  | BytesFieldDescriptor
+  | JsonFieldDescriptor
+  | FacetFieldDescriptor;
 
-export type TextOption = 'STORED';
-export type NumericOption = 'STORED' | 'INDEXED';
+export type TextOption = 'STORED' | 'FAST';
+export type NumericOption = 'STORED' | 'INDEXED' | 'FAST';
+export type JsonOption = 'STORED' | 'FAST';
+export type FacetOption = 'STORED';
 
 export type Field = number;
 
+export type IngestSummary<Position extends 'index' | 'line'> = {
+  added: number,
+  opstamp: bigint | null,
+  errors: ({ [P in Position]: number } & { message: string })[],
+}
+
+export type CommitMeta = {
+  opstamp: bigint,
+  payload: string | null,
+}
+
+export type SchemaOptions = {
+  key?: string,
+}
+
 export type SchemaDescriptor = {
   [key: string]: FieldDescriptor
 };
 
 export type ReloadPolicy = 'COMMIT_WITH_DELAY' | 'MANUAL';
 
+export type OpenMode = 'CREATE' | 'OPEN' | 'OPEN_OR_CREATE' | 'READ_ONLY';
+
 export type IndexOptions = {
+  // The heap size of the index writer in bytes, shared between its
+  // threads. Defaults to 50MB (previously 10MB), since Tantivy requires at
+  // least 15MB per writer thread.
   heapSize?: number,
+  // The number of writer threads. By default, Tantivy chooses it based on
+  // the number of CPUs and the heap size.
+  numThreads?: number,
+  // Sets the heap size per writer thread instead of `heapSize`. Requires
+  // `numThreads`.
+  heapSizePerThread?: number,
   reloadOn?: ReloadPolicy,
+  mode?: OpenMode,
+  inMemory?: boolean,
+  mergePolicy?: MergePolicy,
+}
+
+export type LogMergePolicy = {
+  type: 'log',
+  minNumSegments?: number,
+  maxDocsBeforeMerge?: number,
+  minLayerSize?: number,
+  levelLogSize?: number,
+  delDocsRatioBeforeMerge?: number,
 }
 
+export type NoMergePolicy = {
+  type: 'noMerge',
+}
+
+export type MergePolicy = LogMergePolicy | NoMergePolicy;
+
+export type GarbageCollection = {
+  deletedFiles: string[],
+  failedToDeleteFiles: string[],
+}
+
+export type DateFormat = 'ISO' | 'DATE';
+
 export type SearchOptions = {
-  top?: number
+  // The number of hits to return, at least 1 except in facet searches.
+  top?: number,
+  dates?: DateFormat,
+  // The default fields of a query string, as in `ParseQueryOptions`.
+  defaultFields?: string[],
+}
+
+export type Occur = 'MUST' | 'SHOULD' | 'MUST_NOT';
+
+export type BooleanClause = {
+  occur: Occur,
+  query: Query,
+};
+
+export type BooleanQueryOptions = {
+  // The number of SHOULD clauses that a document must match.
+  minimumShouldMatch?: number,
+};
+
+export type ParseQueryOptions = {
+  // The fields searched by terms without a field name, which default to all
+  // indexed text fields.
+  defaultFields?: string[],
+  conjunctionByDefault?: boolean,
+  fieldBoosts?: { [field: string]: number },
+  fuzzy?: { [field: string]: FuzzyTermQueryOptions },
+};
+
+// A query parsed leniently, with the errors of the parts that were ignored.
+export type LenientQuery = {
+  query: Query,
+  errors: string[],
+};
+
+// Dates may be given as JS Dates, RFC 3339 strings, or epoch milliseconds.
+export type DateValue = Date | string | number;
+
+// Bounds are interpreted according to the type of the field. Bounds of text
+// fields are compared with the indexed terms without being tokenized.
+export type RangeBound = DateValue | bigint | boolean;
+
+// Terms are interpreted according to the type of the field. On IP address
+// fields, a CIDR block such as "10.0.0.0/8" matches every address in it.
+export type TermValue = string | number | bigint | boolean | Date | BytesValue;
+
+export type RangeBounds = {
+  gt?: RangeBound,
+  gte?: RangeBound,
+  lt?: RangeBound,
+  lte?: RangeBound,
 }
 
 export type FuzzyTermQueryOptions = {
@@ -139,6 +375,38 @@ export type FuzzyTermQueryOptions = {
   isPrefix?: boolean,
 };
 
-export type SearchResult = [number, string];
+// Since a document may contain duplicate fields, each field is
+// represented as an array of values. Values of i64 and u64 fields
+// are returned as BigInts so they don't lose precision, and values
+// of date fields are returned according to `SearchOptions.dates`.
+// Values of bytes fields are returned as Buffers.
+//
+// BREAKING: search results used to contain documents as JSON strings,
+// which couldn't represent BigInts; they are now objects, so callers
+// must no longer `JSON.parse` them.
+export type Document = {
+  [key: string]: any[]
+};
+
+export type SearchResult = [number, Document, any];
+
+// Counts of the direct children of each requested facet prefix, keyed
+// by prefix. A search with `top: 0` returns only the facet counts.
+export type FacetSearchResult = {
+  hits: SearchResult[],
+  facets: { [prefix: string]: [string, number][] },
+};
+
+import { Index, Schema } from "./load.cjs";
+
+// Neon classes can't declare static methods, so the factories for opening
+// an existing index are defined here in terms of the constructor's `mode`.
+Index.open = function open(path: string, options?: IndexOptions): Index {
+  return new Index(path, null, { ...options, mode: 'OPEN' });
+};
+
+Index.openOrCreate = function openOrCreate(path: string, schema: Schema, options?: IndexOptions): Index {
+  return new Index(path, schema, { ...options, mode: 'OPEN_OR_CREATE' });
+};
 
-export { Index, Searcher, Query, Schema, TextAnalyzer } from "./load.cjs";
+export { Index, Searcher, Query, Schema, TextAnalyzer, PreparedCommit } from "./load.cjs";
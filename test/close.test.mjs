@@ -0,0 +1,54 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import addon, { OPTIONS, count, tempIndexPath, titleSchema } from './support.mjs';
+
+const { Index } = addon;
+
+function closed(err) {
+  assert.equal(err.code, "INDEX_CLOSED");
+  return true;
+}
+
+test('a closed index and its searchers throw', async () => {
+  const index = new Index(tempIndexPath(), titleSchema(), OPTIONS);
+  index.addDocument({ title: "Emma" });
+  await index.commit();
+  index.reloadSync();
+  const searcher = index.searcher();
+  const query = searcher.regexpQuery(".*", "title");
+  await index.close();
+  await index.close();
+  assert.throws(() => index.addDocument({ title: "Persuasion" }), closed);
+  assert.throws(() => index.searcher(), closed);
+  assert.throws(() => index.schema(), closed);
+  assert.throws(() => searcher.searchSync(query, { top: 10 }), closed);
+  assert.throws(() => searcher.termQuery("Emma", "title"), closed);
+  assert.throws(() => searcher.allQuery(), closed);
+  assert.throws(() => searcher.booleanQuery([{ occur: "MUST", query }]), closed);
+  assert.throws(() => searcher.boostQuery(query, 2), closed);
+  assert.throws(() => searcher.constScoreQuery(query, 1), closed);
+  assert.throws(() => searcher.disjunctionMaxQuery([query]), closed);
+});
+
+test('closing an index releases its directory lock', () => {
+  const path = tempIndexPath();
+  const index = new Index(path, titleSchema(), OPTIONS);
+  index.addDocument({ title: "Emma" });
+  index.commitSync();
+  index.addDocument({ title: "Persuasion" });
+  index.closeSync();
+  const reopened = new Index(path, null, { ...OPTIONS, mode: "OPEN" });
+  // Operations that weren't committed before closing are discarded.
+  assert.equal(count(reopened), 1);
+  reopened.addDocument({ title: "Persuasion" });
+  reopened.commitSync();
+  assert.equal(count(reopened), 2);
+});
+
+test('an index with a pending prepared commit cannot be closed', () => {
+  const index = new Index(tempIndexPath(), titleSchema(), OPTIONS);
+  const prepared = index.prepareCommit();
+  assert.throws(() => index.closeSync(), (err) => err.code === "LOCK_BUSY");
+  prepared.commitSync();
+  index.closeSync();
+});
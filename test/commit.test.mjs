@@ -0,0 +1,55 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import addon, { tempIndexPath } from './support.mjs';
+
+const { Index, Schema } = addon;
+
+const OPTIONS = { heapSize: 50_000_000 };
+
+function titleIndex() {
+  return new Index(tempIndexPath(), new Schema({ title: { type: "text", flags: ["STORED"] } }), OPTIONS);
+}
+
+async function count(index) {
+  await index.reload();
+  const searcher = index.searcher();
+  return searcher.searchSync(searcher.regexpQuery(".*", "title"), { top: 10 }).length;
+}
+
+test('rollback discards uncommitted operations', async () => {
+  const index = titleIndex();
+  index.addDocument({ title: "Emma" });
+  await index.commit();
+  index.addDocument({ title: "Persuasion" });
+  assert.equal(typeof await index.rollback(), "bigint");
+  index.addDocument({ title: "Sanditon" });
+  index.rollbackSync();
+  await index.commit();
+  assert.equal(await count(index), 1);
+});
+
+test('a prepared commit becomes visible with its payload once committed', async () => {
+  const index = titleIndex();
+  index.addDocument({ title: "Emma" });
+  const prepared = index.prepareCommit();
+  assert.throws(() => index.addDocument({ title: "Persuasion" }), /prepared commit is pending/);
+  prepared.setPayload("batch 1");
+  assert.equal(await prepared.commit(), prepared.opstamp());
+  assert.deepEqual(index.lastCommit(), { opstamp: prepared.opstamp(), payload: "batch 1" });
+  assert.throws(() => prepared.commitSync(), /already been resolved/);
+  assert.equal(await count(index), 1);
+});
+
+test('an aborted commit discards its operations', async () => {
+  const index = titleIndex();
+  index.addDocument({ title: "Emma" });
+  index.prepareCommit().abortSync();
+  index.addDocument({ title: "Persuasion" });
+  await index.commit();
+  assert.equal(await count(index), 1);
+  assert.equal(index.lastCommit().payload, null);
+});
+
+test('prepared commits cannot be constructed directly', () => {
+  assert.throws(() => new addon.PreparedCommit(), TypeError);
+});
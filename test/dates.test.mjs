@@ -0,0 +1,43 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import addon, { searcherFor } from './support.mjs';
+
+const { Schema } = addon;
+
+test('dates keep millisecond precision by default', async () => {
+  const schema = new Schema({
+    tag: { type: "string" },
+    at: { type: "date", flags: ["STORED", "INDEXED"] },
+  });
+  const at = new Date("2024-05-06T07:08:09.123Z");
+  const searcher = await searcherFor(schema, [{ tag: "x", at }]);
+  const query = searcher.termQuery("x", "tag");
+  const [[, iso]] = searcher.searchSync(query, { top: 10 });
+  assert.deepEqual(iso.at, ["2024-05-06T07:08:09.123Z"]);
+  const [[, date]] = searcher.searchSync(query, { top: 10, dates: "DATE" });
+  assert.deepEqual(date.at, [at]);
+});
+
+test('dates accept Date objects, ISO strings and epoch milliseconds', async () => {
+  const schema = new Schema({
+    at: { type: "date", flags: ["STORED", "INDEXED"] },
+  });
+  const searcher = await searcherFor(schema, [
+    { at: new Date("2020-01-01T00:00:00Z") },
+    { at: "2021-01-01T00:00:00Z" },
+    { at: Date.parse("2022-01-01T00:00:00Z") },
+  ]);
+  const query = searcher.rangeQuery("at", {
+    gte: "2021-01-01T00:00:00Z",
+    lt: new Date("2022-01-01T00:00:00Z"),
+  });
+  const results = searcher.searchSync(query, { top: 10 });
+  assert.deepEqual(results.map(([, doc]) => doc.at[0]), ["2021-01-01T00:00:00Z"]);
+});
+
+test('range bounds reject conflicting or mistyped bounds', async () => {
+  const schema = new Schema({ at: { type: "date", flags: ["INDEXED"] } });
+  const searcher = await searcherFor(schema, []);
+  assert.throws(() => searcher.rangeQuery("at", { gt: 0, gte: 0 }), /both 'gt' and 'gte'/);
+  assert.throws(() => searcher.rangeQuery("at", { gt: true }));
+});
@@ -0,0 +1,48 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import addon, { tempIndexPath } from './support.mjs';
+
+const { Index, Schema } = addon;
+
+async function deletionIndex() {
+  const schema = new Schema({
+    id: { type: "string", flags: ["STORED"] },
+    year: { type: "u64", flags: ["STORED", "INDEXED"] },
+    title: { type: "text", flags: ["STORED"] },
+  });
+  const index = new Index(tempIndexPath(), schema, { heapSize: 50_000_000 });
+  index.addDocument({ id: "emma", year: 1815n, title: "Emma" });
+  index.addDocument({ id: "persuasion", year: 1817n, title: "Persuasion" });
+  index.addDocument({ id: "northanger", year: 1817n, title: "Northanger Abbey" });
+  await index.commit();
+  return index;
+}
+
+async function remaining(index) {
+  await index.reload();
+  const searcher = index.searcher();
+  return searcher.searchSync(searcher.regexpQuery(".*", "id"), { top: 10 }).map(([, doc]) => doc.id[0]).sort();
+}
+
+test('deleteTerm removes documents by raw or numeric term', async () => {
+  const index = await deletionIndex();
+  assert.equal(typeof index.deleteTerm("id", "emma"), "bigint");
+  await index.commit();
+  assert.deepEqual(await remaining(index), ["northanger", "persuasion"]);
+  index.deleteTerm("year", 1817n);
+  await index.commit();
+  assert.deepEqual(await remaining(index), []);
+});
+
+test('deleteTerm rejects tokenized text fields', async () => {
+  const index = await deletionIndex();
+  assert.throws(() => index.deleteTerm("title", "Emma"), { name: "TypeError", code: "SCHEMA_MISMATCH" });
+});
+
+test('deleteQuery removes matching documents', async () => {
+  const index = await deletionIndex();
+  const searcher = index.searcher();
+  index.deleteQuery(searcher.termQuery("abbey", "title"));
+  await index.commit();
+  assert.deepEqual(await remaining(index), ["emma", "persuasion"]);
+});
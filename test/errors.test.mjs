@@ -0,0 +1,40 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import addon, { OPTIONS, searcherFor, tempIndexPath } from './support.mjs';
+
+const { Index, Schema } = addon;
+
+function code(expected) {
+  return (err) => {
+    assert.equal(err.code, expected);
+    return true;
+  };
+}
+
+test('errors carry a code', () => {
+  const path = tempIndexPath();
+  const schema = new Schema({ title: { type: "text", flags: ["STORED"] } });
+  new Index(path, schema, { ...OPTIONS, mode: "CREATE" });
+  const index = new Index(path, null, { mode: "READ_ONLY" });
+  assert.throws(() => index.addDocument({ title: "Emma" }), code("READ_ONLY"));
+  const searcher = index.searcher();
+  assert.throws(() => searcher.regexpQuery("(", "title"), code("QUERY_PARSE"));
+  assert.throws(() => searcher.regexpQuery(".*", "author"), code("FIELD_NOT_FOUND"));
+});
+
+test('invalid arguments throw INVALID_ARGUMENT', async () => {
+  const schema = new Schema({ title: { type: "text", flags: ["STORED"] } });
+  const searcher = await searcherFor(schema, [{ title: "Emma" }]);
+  const query = searcher.termQuery("emma", "title");
+  assert.equal(searcher.searchSync(query).length, 1);
+  assert.equal((await searcher.search(query, undefined)).length, 1);
+  assert.throws(() => searcher.searchSync(query, { top: 0 }), code("INVALID_ARGUMENT"));
+  assert.throws(() => searcher.searchSync(query, { top: -1 }), code("INVALID_ARGUMENT"));
+  await assert.rejects(searcher.search(query, { top: 0 }), code("INVALID_ARGUMENT"));
+  assert.throws(() => searcher.searchSync(query, { top: "ten" }), code("INVALID_ARGUMENT"));
+  assert.throws(() => searcher.searchSync(42), code("INVALID_ARGUMENT"));
+  assert.throws(() => searcher.booleanQuery(["emma"]), code("INVALID_ARGUMENT"));
+  assert.throws(() => searcher.booleanQuery([{ occur: "MAYBE", query }]), code("INVALID_ARGUMENT"));
+  assert.throws(() => searcher.booleanQuery([{ occur: "MUST", query: "emma" }]), code("INVALID_ARGUMENT"));
+  assert.throws(() => searcher.disjunctionMaxQuery([query, {}]), code("INVALID_ARGUMENT"));
+});
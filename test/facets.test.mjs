@@ -0,0 +1,51 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import addon, { tempIndexPath } from './support.mjs';
+
+const { Index, Schema } = addon;
+
+async function facetIndex() {
+  const schema = new Schema({
+    title: { type: "text", flags: ["STORED"] },
+    category: { type: "facet", flags: ["STORED"] },
+  });
+  const index = new Index(tempIndexPath(), schema, { heapSize: 50_000_000 });
+  index.addDocument({ title: "emma", category: "/fiction/novel" });
+  index.addDocument({ title: "persuasion", category: "/fiction/novel" });
+  index.addDocument({ title: "juvenilia", category: "/fiction/stories" });
+  index.addDocument({ title: "letters", category: "/nonfiction/letters" });
+  await index.commit();
+  await index.reload();
+  return index.searcher();
+}
+
+test('facet search counts the children of each prefix', async () => {
+  const searcher = await facetIndex();
+  const query = searcher.regexpQuery(".*", "title");
+  const result = await searcher.facetSearch(query, "category", ["/fiction", "/nonfiction"], { top: 10 });
+  assert.equal(result.hits.length, 4);
+  assert.deepEqual(result.facets, {
+    "/fiction": [["/fiction/novel", 2], ["/fiction/stories", 1]],
+    "/nonfiction": [["/nonfiction/letters", 1]],
+  });
+  const counts = searcher.facetSearchSync(query, "category", ["/"], { top: 0 });
+  assert.deepEqual(counts.hits, []);
+  assert.deepEqual(counts.facets["/"], [["/fiction", 3], ["/nonfiction", 1]]);
+});
+
+test('facet search rejects overlapping prefixes and non-facet fields', async () => {
+  const searcher = await facetIndex();
+  const query = searcher.regexpQuery(".*", "title");
+  assert.throws(() => searcher.facetSearchSync(query, "category", ["/fiction", "/fiction/novel"], { top: 0 }), /overlap/);
+  assert.throws(() => searcher.facetSearchSync(query, "title", ["/"], { top: 0 }), TypeError);
+  await assert.rejects(searcher.facetSearch(query, "title", ["/"], { top: 0 }), TypeError);
+});
+
+test('facet search options are optional', async () => {
+  const searcher = await facetIndex();
+  const query = searcher.regexpQuery(".*", "title");
+  const result = await searcher.facetSearch(query, "category", ["/nonfiction"]);
+  assert.equal(result.hits.length, 4);
+  assert.deepEqual(result.facets["/nonfiction"], [["/nonfiction/letters", 1]]);
+  assert.equal(searcher.facetSearchSync(query, "category", ["/"]).hits.length, 4);
+});
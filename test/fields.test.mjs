@@ -0,0 +1,108 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import addon, { tempIndexPath } from './support.mjs';
+
+const { Index, Schema } = addon;
+
+async function searchAll(index, docs) {
+  for (const doc of docs) {
+    index.addDocument(doc);
+  }
+  await index.commit();
+  await index.reload();
+  const searcher = index.searcher();
+  return searcher.search(searcher.termQuery("x", "tag"), { top: 100 });
+}
+
+test('i64 and u64 values round-trip as BigInts', async () => {
+  const schema = new Schema({
+    tag: { type: "string" },
+    signed: { type: "i64", flags: ["STORED", "INDEXED"] },
+    unsigned: { type: "u64", flags: ["STORED"] },
+  });
+  const index = new Index(tempIndexPath(), schema, { heapSize: 50_000_000 });
+  const results = await searchAll(index, [
+    { tag: "x", signed: -(2n ** 62n), unsigned: 2n ** 64n - 1n },
+  ]);
+  assert.equal(results.length, 1);
+  const [_score, doc] = results[0];
+  assert.deepEqual(doc.signed, [-(2n ** 62n)]);
+  assert.deepEqual(doc.unsigned, [2n ** 64n - 1n]);
+});
+
+test('integer fields accept safe JS numbers', async () => {
+  const schema = new Schema({
+    tag: { type: "string" },
+    signed: { type: "i64", flags: ["STORED"] },
+  });
+  const index = new Index(tempIndexPath(), schema, { heapSize: 50_000_000 });
+  const results = await searchAll(index, [{ tag: "x", signed: -42 }]);
+  assert.deepEqual(results[0][1].signed, [-42n]);
+});
+
+test('integer fields reject unsafe or fractional JS numbers', () => {
+  const schema = new Schema({
+    signed: { type: "i64", flags: ["STORED"] },
+    unsigned: { type: "u64", flags: ["STORED"] },
+  });
+  const index = new Index(tempIndexPath(), schema, { heapSize: 50_000_000 });
+  assert.throws(() => index.addDocument({ signed: 2 ** 60 }), RangeError);
+  assert.throws(() => index.addDocument({ signed: 1.5 }), RangeError);
+  assert.throws(() => index.addDocument({ unsigned: -1 }), RangeError);
+  assert.throws(() => index.addDocument({ unsigned: -1n }), RangeError);
+});
+
+test('bool and IP address fields', async () => {
+  const schema = new Schema({
+    tag: { type: "string" },
+    active: { type: "bool", flags: ["STORED", "INDEXED"] },
+    addr: { type: "ipAddr", flags: ["STORED", "INDEXED"] },
+  });
+  const index = new Index(tempIndexPath(), schema, { heapSize: 50_000_000 });
+  const results = await searchAll(index, [
+    { tag: "x", active: true, addr: "10.1.2.3" },
+    { tag: "x", active: false, addr: "192.168.0.1" },
+    { tag: "x", active: true, addr: "2001:db8::1" },
+  ]);
+  assert.equal(results.length, 3);
+  const searcher = index.searcher();
+  const addrs = (query) => searcher.searchSync(query, { top: 10 }).map(([, doc]) => doc.addr[0]).sort();
+  assert.deepEqual(addrs(searcher.termQuery(true, "active")), ["10.1.2.3", "2001:db8::1"]);
+  assert.deepEqual(addrs(searcher.termQuery("10.0.0.0/8", "addr")), ["10.1.2.3"]);
+  assert.deepEqual(addrs(searcher.termQuery("2001:db8::/32", "addr")), ["2001:db8::1"]);
+  assert.deepEqual(addrs(searcher.termQuery("192.168.0.1", "addr")), ["192.168.0.1"]);
+  assert.deepEqual(addrs(searcher.rangeQuery("addr", { gte: "10.0.0.0", lte: "192.168.0.1" })), ["10.1.2.3", "192.168.0.1"]);
+  assert.throws(() => searcher.termQuery("10.0.0.0/40", "addr"));
+});
+
+test('bytes values round-trip as Buffers', async () => {
+  const schema = new Schema({
+    tag: { type: "string" },
+    data: { type: "bytes", flags: ["STORED", "INDEXED"] },
+  });
+  const index = new Index(tempIndexPath(), schema, { heapSize: 50_000_000 });
+  const results = await searchAll(index, [
+    { tag: "x", data: Buffer.from([1, 2, 3]) },
+    { tag: "x", data: new Uint8Array([4, 5]) },
+    { tag: "x", data: Buffer.from([6]).toString("base64") },
+  ]);
+  const values = results.map(([, doc]) => doc.data[0]);
+  assert.ok(values.every((value) => Buffer.isBuffer(value)));
+  assert.deepEqual(values.map((value) => [...value]).sort(), [[1, 2, 3], [4, 5], [6]]);
+  const searcher = index.searcher();
+  const [[, doc]] = searcher.searchSync(searcher.termQuery(new Uint8Array([4, 5]), "data"), { top: 10 });
+  assert.deepEqual([...doc.data[0]], [4, 5]);
+});
+
+test('FAST flags are surfaced by Schema.fields()', () => {
+  const descriptors = {
+    title: { type: "text", flags: ["STORED", "FAST"], fastTokenizer: "raw" },
+    year: { type: "u64", flags: ["INDEXED", "FAST"] },
+  };
+  const schema = new Schema(descriptors);
+  const fields = schema.fields();
+  assert.deepEqual(fields.title.flags, ["STORED", "FAST"]);
+  assert.equal(fields.title.fastTokenizer, "raw");
+  assert.deepEqual(fields.year.flags, ["INDEXED", "FAST"]);
+  new Index(tempIndexPath(), schema, { heapSize: 50_000_000 });
+});
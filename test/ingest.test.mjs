@@ -0,0 +1,63 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import { writeFileSync } from 'node:fs';
+import * as path from 'node:path';
+import addon, { tempIndexPath } from './support.mjs';
+
+const { Index, Schema } = addon;
+
+function bookIndex() {
+  const schema = new Schema({
+    title: { type: "text", flags: ["STORED"] },
+    year: { type: "u64", flags: ["STORED", "INDEXED"] },
+    published: { type: "date", flags: ["STORED", "INDEXED"] },
+  });
+  return new Index(tempIndexPath(), schema, { heapSize: 50_000_000 });
+}
+
+async function books(index) {
+  await index.commit();
+  await index.reload();
+  const searcher = index.searcher();
+  return searcher.searchSync(searcher.regexpQuery(".*", "title"), { top: 10 })
+    .map(([, doc]) => doc)
+    .sort((a, b) => a.title[0].localeCompare(b.title[0]));
+}
+
+test('addDocuments adds a batch and reports the documents that fail', async () => {
+  const index = bookIndex();
+  const summary = await index.addDocuments([
+    { title: "emma", year: 1815n },
+    { title: "sanditon", year: 1.5 },
+    "persuasion",
+    { title: "persuasion", year: 1817 },
+  ]);
+  assert.equal(summary.added, 2);
+  assert.equal(typeof summary.opstamp, "bigint");
+  assert.deepEqual(summary.errors.map(error => error.index), [1, 2]);
+  assert.deepEqual((await books(index)).map(doc => doc.year[0]), [1815n, 1817n]);
+});
+
+test('ingestNdjson converts values like addDocument', async () => {
+  const index = bookIndex();
+  const file = path.join(tempIndexPath(), "books.ndjson");
+  writeFileSync(file, [
+    '{"title": "emma", "year": 18446744073709551615, "published": 0}',
+    '',
+    '{"title": "persuasion", "year": 1817.5}',
+    'not json',
+    '{"title": "sanditon", "published": "1817-03-18T00:00:00Z"}',
+  ].join("\n"));
+  const summary = await index.ingestNdjson(file);
+  assert.equal(summary.added, 2);
+  assert.deepEqual(summary.errors.map(error => error.line), [3, 4]);
+
+  const buffered = await index.ingestNdjson(Buffer.from('{"title": "lady susan", "year": 1871}\n'));
+  assert.deepEqual(buffered.errors, []);
+
+  const [emma, lady, sanditon] = await books(index);
+  assert.deepEqual(emma.year, [18446744073709551615n]);
+  assert.deepEqual(emma.published, ["1970-01-01T00:00:00Z"]);
+  assert.deepEqual(lady.year, [1871n]);
+  assert.deepEqual(sanditon.published, ["1817-03-18T00:00:00Z"]);
+});
@@ -0,0 +1,51 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import addon, { searcherFor } from './support.mjs';
+
+const { Schema } = addon;
+
+const ids = (searcher, query) =>
+  searcher.searchSync(query, { top: 10 }).map(([, doc]) => doc.id[0]).sort();
+
+test('term queries on JSON paths are tokenized and typed', async () => {
+  const schema = new Schema({
+    id: { type: "string", flags: ["STORED"] },
+    attrs: { type: "json", flags: ["STORED"] },
+  });
+  const searcher = await searcherFor(schema, [
+    { id: "a", attrs: { color: "Dark Red", size: 42 } },
+    { id: "b", attrs: { color: "red", size: 7 } },
+    { id: "c", attrs: { color: "blue", size: "42" } },
+  ]);
+  assert.deepEqual(ids(searcher, searcher.termQuery("Red", "attrs.color")), ["a", "b"]);
+  assert.deepEqual(ids(searcher, searcher.termQuery("dark red", "attrs.color")), ["a"]);
+  assert.deepEqual(ids(searcher, searcher.termQuery("42", "attrs.size")), ["a", "c"]);
+  assert.deepEqual(ids(searcher, searcher.rangeQuery("attrs.size", { gte: 5, lt: 10 })), ["b"]);
+});
+
+test('term queries on JSON paths accept numbers and booleans', async () => {
+  const schema = new Schema({
+    id: { type: "string", flags: ["STORED"] },
+    attrs: { type: "json", flags: ["STORED"] },
+  });
+  const searcher = await searcherFor(schema, [
+    { id: "a", attrs: { size: 42, flag: true } },
+    { id: "b", attrs: { size: 7.5, flag: false } },
+  ]);
+  assert.deepEqual(ids(searcher, searcher.termQuery(42, "attrs.size")), ["a"]);
+  assert.deepEqual(ids(searcher, searcher.termQuery(7.5, "attrs.size")), ["b"]);
+  assert.deepEqual(ids(searcher, searcher.termQuery(true, "attrs.flag")), ["a"]);
+  assert.deepEqual(ids(searcher, searcher.termQuery(false, "attrs.flag")), ["b"]);
+  assert.throws(() => searcher.termQuery({}, "attrs.size"), { code: "INVALID_ARGUMENT" });
+});
+
+test('JSON fields can opt out of indexing', async () => {
+  const schema = new Schema({
+    id: { type: "string", flags: ["STORED"] },
+    attrs: { type: "json", flags: ["STORED"], indexed: false },
+  });
+  const searcher = await searcherFor(schema, [{ id: "a", attrs: { color: "red" } }]);
+  assert.throws(() => searcher.termQuery("red", "attrs.color"), /not indexed/);
+  const [[, doc]] = searcher.searchSync(searcher.termQuery("a", "id"), { top: 10 });
+  assert.deepEqual(doc.attrs, [{ color: "red" }]);
+});
@@ -0,0 +1,45 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import addon, { OPTIONS, count, tempIndexPath, titleSchema } from './support.mjs';
+
+const { Index } = addon;
+
+const NO_MERGE = { ...OPTIONS, mergePolicy: { type: "noMerge" } };
+
+test('merge combines segments into one', async () => {
+  const index = new Index(tempIndexPath(), titleSchema(), NO_MERGE);
+  for (const title of ["Emma", "Persuasion"]) {
+    index.addDocument({ title });
+    await index.commit();
+  }
+  const segments = index.segmentIds();
+  assert.equal(segments.length, 2);
+  const merged = await index.merge(segments);
+  assert.deepEqual(index.segmentIds(), [merged]);
+  assert.equal(count(index), 2);
+  const gc = await index.garbageCollectFiles();
+  assert.deepEqual(gc.failedToDeleteFiles, []);
+});
+
+test('waitMergingThreads keeps the index writable', async () => {
+  const index = new Index(tempIndexPath(), titleSchema(), NO_MERGE);
+  index.addDocument({ title: "Emma" });
+  await index.commit();
+  await index.waitMergingThreads();
+  assert.equal(count(index), 1);
+  index.addDocument({ title: "Persuasion" });
+  await index.commit();
+  index.waitMergingThreadsSync();
+  assert.equal(count(index), 2);
+});
+
+test('waitMergingThreads discards uncommitted operations', async () => {
+  const index = new Index(tempIndexPath(), titleSchema(), NO_MERGE);
+  index.addDocument({ title: "Emma" });
+  await index.commit();
+  index.addDocument({ title: "Persuasion" });
+  await index.waitMergingThreads();
+  assert.equal(count(index), 1);
+  await index.commit();
+  assert.equal(count(index), 1);
+});
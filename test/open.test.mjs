@@ -0,0 +1,69 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import { execFileSync } from 'node:child_process';
+import addon, { tempIndexPath } from './support.mjs';
+
+const { Index, Schema } = addon;
+
+const OPTIONS = { heapSize: 50_000_000 };
+
+test('an existing index can be reopened with its schema', async () => {
+  const path = tempIndexPath();
+  const descriptors = {
+    title: { type: "text", flags: ["STORED"] },
+    year: { type: "u64", flags: ["STORED", "INDEXED"] },
+  };
+  // Build the index in another process, which releases the writer's lock on exit.
+  execFileSync(process.execPath, ["--input-type=module", "-e", `
+    import addon from ${JSON.stringify(new URL('./support.mjs', import.meta.url).href)};
+    const index = new addon.Index(${JSON.stringify(path)}, new addon.Schema(${JSON.stringify(descriptors)}), ${JSON.stringify(OPTIONS)});
+    index.addDocument({ title: "Emma", year: 1815n });
+    await index.commit();
+  `]);
+
+  const index = new Index(path, null, { ...OPTIONS, mode: "OPEN" });
+  assert.deepEqual(Object.keys(index.schema().fields()), ["title", "year"]);
+  assert.deepEqual(index.schema().fields().year.flags, ["STORED", "INDEXED"]);
+  await index.reload();
+  const searcher = index.searcher();
+  const [[, doc]] = searcher.searchSync(searcher.termQuery("emma", "title"), { top: 1 });
+  assert.deepEqual(doc.year, [1815n]);
+});
+
+test('open modes validate the directory and schema', () => {
+  const schema = new Schema({ title: { type: "text" } });
+  assert.throws(() => new Index(tempIndexPath(), null, { ...OPTIONS, mode: "OPEN" }), { code: "INDEX_NOT_FOUND" });
+  assert.throws(() => new Index(`${tempIndexPath()}/missing`, null, { ...OPTIONS, mode: "OPEN" }), { code: "INDEX_NOT_FOUND" });
+  assert.throws(() => new Index(tempIndexPath(), null, { ...OPTIONS, mode: "OPEN_OR_CREATE" }), /schema is required/);
+
+  const path = tempIndexPath();
+  new Index(path, schema, { ...OPTIONS, mode: "OPEN_OR_CREATE" });
+  const other = new Schema({ body: { type: "text" } });
+  assert.throws(() => new Index(path, other, { ...OPTIONS, mode: "OPEN_OR_CREATE" }), /does not match/);
+  assert.throws(() => new Index(path, schema, OPTIONS));
+});
+
+test('a null path creates an in-memory index', async () => {
+  const index = new Index(null, new Schema({ title: { type: "text", flags: ["STORED"] } }), OPTIONS);
+  index.addDocument({ title: "Persuasion" });
+  await index.commit();
+  await index.reload();
+  const searcher = index.searcher();
+  assert.equal(searcher.searchSync(searcher.termQuery("persuasion", "title"), { top: 1 }).length, 1);
+});
+
+test('a read-only index can search but not write', async () => {
+  const path = tempIndexPath();
+  const schema = new Schema({ title: { type: "text", flags: ["STORED"] } });
+  const writer = new Index(path, schema, OPTIONS);
+  writer.addDocument({ title: "Sanditon" });
+  await writer.commit();
+
+  // No writer lock is taken, so this works alongside the writing index.
+  const reader = new Index(path, null, { mode: "READ_ONLY", reloadOn: "MANUAL" });
+  await reader.reload();
+  const searcher = reader.searcher();
+  assert.equal(searcher.searchSync(searcher.termQuery("sanditon", "title"), { top: 1 }).length, 1);
+  assert.throws(() => reader.addDocument({ title: "Emma" }), /read-only/);
+  await assert.rejects(reader.commit(), /read-only/);
+});
@@ -0,0 +1,213 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import addon, { searcherFor } from './support.mjs';
+
+const { Index, Schema } = addon;
+
+async function bookIndex() {
+  const schema = new Schema({
+    title: { type: "text", flags: ["STORED"] },
+  });
+  const index = new Index(null, schema, {});
+  for (const title of ["Emma", "Persuasion", "Sense and Sensibility"]) {
+    index.addDocument({ title });
+  }
+  await index.commit();
+  await index.reload();
+  return index;
+}
+
+function titles(results) {
+  return results.map(([, doc]) => doc.title[0]).sort();
+}
+
+test('fuzzy term query options are camelCase and optional', async () => {
+  const searcher = (await bookIndex()).searcher();
+  const exact = searcher.fuzzyTermQuery("emm", "title", {});
+  assert.deepEqual(searcher.searchSync(exact, { top: 10 }), []);
+  const fuzzy = searcher.fuzzyTermQuery("emm", "title", { maxDistance: 1 });
+  assert.deepEqual(titles(searcher.searchSync(fuzzy, { top: 10 })), ["Emma"]);
+  const prefix = searcher.fuzzyTermQuery("persua", "title", { isPrefix: true });
+  assert.deepEqual(titles(searcher.searchSync(prefix, { top: 10 })), ["Persuasion"]);
+});
+
+test('query strings are parsed with the tokenizers of the index', async () => {
+  const searcher = (await bookIndex()).searcher();
+  const query = searcher.parseQuery("title:emma OR sensibility");
+  assert.deepEqual(titles(searcher.searchSync(query, { top: 10 })), ["Emma", "Sense and Sensibility"]);
+  const conjunction = searcher.parseQuery("sense sensibility", { conjunctionByDefault: true, defaultFields: ["title"] });
+  assert.deepEqual(titles(searcher.searchSync(conjunction, { top: 10 })), ["Sense and Sensibility"]);
+  const fuzzy = searcher.parseQuery("persuasian", { fuzzy: { title: { maxDistance: 1 } } });
+  assert.deepEqual(titles(searcher.searchSync(fuzzy, { top: 10 })), ["Persuasion"]);
+  assert.throws(() => searcher.parseQuery("title:(emma"), (err) => err.code === "QUERY_PARSE");
+  assert.throws(() => searcher.parseQuery("author:austen"), (err) => err.code === "FIELD_NOT_FOUND");
+});
+
+test('lenient parsing reports the parts it ignored', async () => {
+  const searcher = (await bookIndex()).searcher();
+  const { query, errors } = searcher.parseQueryLenient("emma author:austen");
+  assert.deepEqual(titles(searcher.searchSync(query, { top: 10 })), ["Emma"]);
+  assert.equal(errors.length, 1);
+});
+
+test('searches accept query strings', async () => {
+  const searcher = (await bookIndex()).searcher();
+  assert.deepEqual(titles(searcher.searchSync("emma persuasion", { top: 10 })), ["Emma", "Persuasion"]);
+  assert.deepEqual(titles(await searcher.search("emma", { top: 10, defaultFields: ["title"] })), ["Emma"]);
+  await assert.rejects(searcher.search("title:(", { top: 10 }), (err) => err.code === "QUERY_PARSE");
+  await assert.rejects(searcher.search(42), (err) => err.code === "INVALID_ARGUMENT");
+});
+
+test('search options reject unknown fields', async () => {
+  const searcher = (await bookIndex()).searcher();
+  assert.throws(() => searcher.searchSync("emma", { fields: ["title"] }), /unknown field `fields`/);
+  await assert.rejects(searcher.search("emma", { fields: ["title"] }), (err) => err.code === "INVALID_ARGUMENT");
+});
+
+test('boolean queries combine clauses', async () => {
+  const searcher = (await bookIndex()).searcher();
+  const term = (text) => searcher.termQuery(text, "title");
+  const query = searcher.booleanQuery([
+    { occur: "SHOULD", query: term("emma") },
+    { occur: "SHOULD", query: term("sense") },
+    { occur: "MUST_NOT", query: term("sensibility") },
+  ]);
+  assert.deepEqual(titles(searcher.searchSync(query, { top: 10 })), ["Emma"]);
+  const must = searcher.booleanQuery([{ occur: "MUST", query: term("persuasion") }]);
+  assert.deepEqual(titles(searcher.searchSync(must, { top: 10 })), ["Persuasion"]);
+  const conjunction = searcher.booleanQuery([
+    { occur: "MUST", query: term("sense") },
+    { occur: "MUST", query: term("sensibility") },
+  ]);
+  const nested = searcher.booleanQuery([
+    { occur: "SHOULD", query: conjunction },
+    { occur: "SHOULD", query: term("emma") },
+  ]);
+  assert.deepEqual(titles(searcher.searchSync(nested, { top: 10 })), ["Emma", "Sense and Sensibility"]);
+});
+
+test('compound clauses can be explained when they do not match', async () => {
+  const searcher = (await bookIndex()).searcher();
+  const query = searcher.booleanQuery([
+    { occur: "SHOULD", query: searcher.phraseQuery(["sense", "and"], "title") },
+    { occur: "SHOULD", query: searcher.parseQuery("sense AND sensibility") },
+    { occur: "SHOULD", query: searcher.termQuery("emma", "title") },
+  ]);
+  const results = searcher.searchSync(query, { top: 10 });
+  assert.deepEqual(titles(results), ["Emma", "Sense and Sensibility"]);
+  assert.ok(results.every(([, , explanation]) => explanation));
+  const minimum = searcher.booleanQuery([
+    { occur: "SHOULD", query: searcher.phraseQuery(["sense", "and"], "title") },
+    { occur: "SHOULD", query: searcher.termQuery("emma", "title") },
+    { occur: "SHOULD", query: searcher.termQuery("persuasion", "title") },
+  ], { minimumShouldMatch: 1 });
+  assert.deepEqual(titles(searcher.searchSync(minimum, { top: 10 })), ["Emma", "Persuasion", "Sense and Sensibility"]);
+});
+
+test('boolean queries can require several SHOULD clauses to match', async () => {
+  const searcher = (await bookIndex()).searcher();
+  const clauses = ["emma", "sense", "and", "sensibility"].map((text) => ({
+    occur: "SHOULD",
+    query: searcher.termQuery(text, "title"),
+  }));
+  const search = (minimumShouldMatch) =>
+    titles(searcher.searchSync(searcher.booleanQuery(clauses, { minimumShouldMatch }), { top: 10 }));
+  assert.deepEqual(search(0), ["Emma", "Sense and Sensibility"]);
+  assert.deepEqual(search(1), ["Emma", "Sense and Sensibility"]);
+  assert.deepEqual(search(3), ["Sense and Sensibility"]);
+  assert.deepEqual(search(5), []);
+  const persuasion = { occur: "MUST", query: searcher.termQuery("persuasion", "title") };
+  assert.deepEqual(titles(searcher.searchSync(searcher.booleanQuery([persuasion, ...clauses], { minimumShouldMatch: 1 }), { top: 10 })), []);
+});
+
+test('queries can be boosted, given constant scores and combined by their best score', async () => {
+  const searcher = (await bookIndex()).searcher();
+  const term = (text) => searcher.termQuery(text, "title");
+  const [[score]] = searcher.searchSync(term("emma"), { top: 1 });
+  const [[boosted]] = searcher.searchSync(searcher.boostQuery(term("emma"), 2), { top: 1 });
+  assert.ok(Math.abs(boosted - 2 * score) < 1e-4);
+  const [[constant]] = searcher.searchSync(searcher.constScoreQuery(term("emma"), 3), { top: 1 });
+  assert.equal(constant, 3);
+  const dismax = searcher.disjunctionMaxQuery([term("emma"), term("sense"), term("sensibility")]);
+  assert.deepEqual(titles(searcher.searchSync(dismax, { top: 10 })), ["Emma", "Sense and Sensibility"]);
+  assert.throws(() => searcher.disjunctionMaxQuery([term("emma")], 2), RangeError);
+  assert.throws(() => searcher.boostQuery(term("emma"), NaN), { code: "INVALID_ARGUMENT" });
+  assert.throws(() => searcher.boostQuery(term("emma"), -1), { code: "INVALID_ARGUMENT" });
+});
+
+test('disjuncts can be explained when they do not match', async () => {
+  const searcher = (await bookIndex()).searcher();
+  const dismax = searcher.disjunctionMaxQuery([
+    searcher.phraseQuery(["sense", "and"], "title"),
+    searcher.parseQuery("sense AND sensibility"),
+    searcher.termQuery("emma", "title"),
+  ]);
+  assert.deepEqual(titles(searcher.searchSync(dismax, { top: 10 })), ["Emma", "Sense and Sensibility"]);
+});
+
+test('range and term queries read values of the field type', async () => {
+  const schema = new Schema({
+    title: { type: "string", flags: ["STORED"] },
+    year: { type: "u64", flags: ["INDEXED"] },
+    delta: { type: "i64", flags: ["INDEXED"] },
+    rating: { type: "f64", flags: ["INDEXED"] },
+  });
+  const index = new Index(null, schema, {});
+  index.addDocument({ title: "Emma", year: 1815, delta: -3, rating: 4.5 });
+  index.addDocument({ title: "Persuasion", year: 1817, delta: 0, rating: 3.9 });
+  index.addDocument({ title: "Sense and Sensibility", year: 1811, delta: 7, rating: 4.1 });
+  await index.commit();
+  await index.reload();
+  const searcher = index.searcher();
+  const search = (query) => titles(searcher.searchSync(query, { top: 10 }));
+  assert.deepEqual(search(searcher.rangeQuery("year", { gte: 1815 })), ["Emma", "Persuasion"]);
+  assert.deepEqual(search(searcher.rangeQuery("year", { lt: 1815n })), ["Sense and Sensibility"]);
+  assert.deepEqual(search(searcher.rangeQuery("delta", { gt: -3, lte: 7 })), ["Persuasion", "Sense and Sensibility"]);
+  assert.deepEqual(search(searcher.rangeQuery("rating", { gte: 4 })), ["Emma", "Sense and Sensibility"]);
+  assert.deepEqual(search(searcher.rangeQuery("title", { gte: "F", lt: "S" })), ["Persuasion"]);
+  assert.deepEqual(search(searcher.termQuery(1817, "year")), ["Persuasion"]);
+  assert.deepEqual(search(searcher.termQuery(-3, "delta")), ["Emma"]);
+  assert.deepEqual(search(searcher.termQuery(4.1, "rating")), ["Sense and Sensibility"]);
+  assert.deepEqual(search(searcher.termQuery("Sense and Sensibility", "title")), ["Sense and Sensibility"]);
+  assert.throws(() => searcher.rangeQuery("year", { gte: "1815" }), { code: "SCHEMA_MISMATCH" });
+  assert.throws(() => searcher.termQuery(true, "year"), { code: "SCHEMA_MISMATCH" });
+});
+
+test('all, exists and term set queries', async () => {
+  const schema = new Schema({
+    title: { type: "string", flags: ["STORED"] },
+    year: { type: "u64", flags: ["INDEXED", "FAST"] },
+    delta: { type: "i64", flags: ["INDEXED"] },
+  });
+  const index = new Index(null, schema, {});
+  index.addDocument({ title: "Emma", year: 1815, delta: -3 });
+  index.addDocument({ title: "Persuasion", delta: 0 });
+  index.addDocument({ title: "Sense and Sensibility", year: 1811, delta: 7 });
+  await index.commit();
+  await index.reload();
+  const searcher = index.searcher();
+  const search = (query) => titles(searcher.searchSync(query, { top: 10 }));
+  assert.deepEqual(search(searcher.allQuery()), ["Emma", "Persuasion", "Sense and Sensibility"]);
+  assert.deepEqual(search(searcher.existsQuery("year")), ["Emma", "Sense and Sensibility"]);
+  assert.throws(() => searcher.existsQuery("delta"), { code: "SCHEMA_MISMATCH" });
+  assert.deepEqual(search(searcher.termSetQuery("delta", [-3, 7, 100])), ["Emma", "Sense and Sensibility"]);
+  assert.deepEqual(search(searcher.termSetQuery("title", ["Emma", "Persuasion"])), ["Emma", "Persuasion"]);
+  assert.deepEqual(search(searcher.termSetQuery("title", [])), []);
+  assert.throws(() => searcher.termSetQuery("delta", ["x"]), { code: "SCHEMA_MISMATCH" });
+});
+
+test('term set queries resolve JSON paths', async () => {
+  const schema = new Schema({
+    id: { type: "string", flags: ["STORED"] },
+    attrs: { type: "json", flags: ["STORED"] },
+  });
+  const searcher = await searcherFor(schema, [
+    { id: "a", attrs: { color: "Red", size: 42 } },
+    { id: "b", attrs: { color: "blue", size: 7 } },
+    { id: "c", attrs: { color: "green", size: "42" } },
+  ]);
+  const ids = (query) => searcher.searchSync(query, { top: 10 }).map(([, doc]) => doc.id[0]).sort();
+  assert.deepEqual(ids(searcher.termSetQuery("attrs.color", ["red", "Blue"])), ["a", "b"]);
+  assert.deepEqual(ids(searcher.termSetQuery("attrs.size", [7, "42"])), ["a", "b", "c"]);
+  assert.throws(() => searcher.termSetQuery("attrs.color", ["dark red"]), { code: "INVALID_ARGUMENT" });
+});
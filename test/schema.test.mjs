@@ -0,0 +1,25 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import addon, { searcherFor } from './support.mjs';
+
+const { Schema } = addon;
+
+test('text fields default to positions unless a tokenizer is given', async () => {
+  const schema = new Schema({
+    plain: { type: "text" },
+    custom: { type: "text", tokenizer: "default" },
+    explicit: { type: "text", tokenizer: "default", index: "WITH_FREQS_AND_POSITIONS" },
+  });
+  const searcher = await searcherFor(schema, [{ plain: "a b", custom: "a b", explicit: "a b" }]);
+  assert.equal(searcher.searchSync(searcher.phraseQuery(["a", "b"], "plain"), { top: 1 }).length, 1);
+  assert.equal(searcher.searchSync(searcher.phraseQuery(["a", "b"], "explicit"), { top: 1 }).length, 1);
+  assert.throws(() => searcher.phraseQuery(["a", "b"], "custom"), /not indexed with positions/);
+});
+
+test('stored-only text fields reject queries and indexing options', async () => {
+  assert.throws(() => new Schema({ body: { type: "text", indexed: false, tokenizer: "raw" } }), /only apply to indexed fields/);
+  assert.throws(() => new Schema({ body: { type: "string", fastTokenizer: "raw" } }), /requires the FAST flag/);
+  const schema = new Schema({ body: { type: "text", flags: ["STORED"], indexed: false } });
+  const searcher = await searcherFor(schema, [{ body: "hello" }]);
+  assert.throws(() => searcher.termQuery("hello", "body"), /not indexed/);
+});
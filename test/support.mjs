@@ -0,0 +1,40 @@
+import { createRequire } from 'node:module';
+import { mkdtempSync } from 'node:fs';
+import { tmpdir } from 'node:os';
+import * as path from 'node:path';
+
+const require = createRequire(import.meta.url);
+
+// The debug build of the addon, as produced by `npm run debug`.
+const addon = require('../index.node');
+
+export default addon;
+
+export function tempIndexPath() {
+  return mkdtempSync(path.join(tmpdir(), 'tantivy-test-'));
+}
+
+// Index options for tests that write to an index on disk.
+export const OPTIONS = { heapSize: 50_000_000 };
+
+export function titleSchema() {
+  return new addon.Schema({ title: { type: "string", flags: ["STORED"] } });
+}
+
+// Counts the documents with a title visible after reloading the index.
+export function count(index) {
+  index.reloadSync();
+  const searcher = index.searcher();
+  return searcher.searchSync(searcher.regexpQuery(".*", "title"), { top: 10 }).length;
+}
+
+// Creates an index with the documents committed and returns its searcher.
+export async function searcherFor(schema, docs) {
+  const index = new addon.Index(tempIndexPath(), schema, OPTIONS);
+  for (const doc of docs) {
+    index.addDocument(doc);
+  }
+  await index.commit();
+  await index.reload();
+  return index.searcher();
+}
@@ -0,0 +1,41 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import addon, { OPTIONS, tempIndexPath } from './support.mjs';
+
+const { Index, Schema } = addon;
+
+function bookSchema() {
+  return new Schema({
+    id: { type: "string", flags: ["STORED"] },
+    title: { type: "text", flags: ["STORED"] },
+  }, { key: "id" });
+}
+
+async function titles(index) {
+  await index.reload();
+  const searcher = index.searcher();
+  return searcher.searchSync(searcher.regexpQuery(".*", "id"), { top: 10 }).map(([, doc]) => doc.title[0]).sort();
+}
+
+test('upsertDocument replaces documents with the same key', async () => {
+  const index = new Index(tempIndexPath(), bookSchema(), OPTIONS);
+  index.upsertDocument(null, { id: "emma", title: "Emma" });
+  index.upsertDocument(null, { id: "emma", title: "Emma, a Novel" });
+  index.upsertDocument("id", { id: "persuasion", title: "Persuasion" });
+  await index.commit();
+  assert.deepEqual(await titles(index), ["Emma, a Novel", "Persuasion"]);
+  assert.throws(() => index.upsertDocument(null, { title: "Untitled" }), /exactly one value/);
+});
+
+test('the key field of a schema is kept when an index is reopened', () => {
+  const path = tempIndexPath();
+  new Index(path, bookSchema(), { ...OPTIONS, mode: "CREATE" });
+  const index = new Index(path, null, { mode: "READ_ONLY" });
+  assert.equal(index.schema().key(), "id");
+});
+
+test('key fields must be indexed without tokenization', () => {
+  assert.throws(() => new Schema({ title: { type: "text" } }, { key: "title" }), /tokenized/);
+  assert.throws(() => new Schema({ year: { type: "u64", flags: ["STORED"] } }, { key: "year" }), /not indexed/);
+  assert.equal(new Schema({ year: { type: "u64", flags: ["INDEXED"] } }, { key: "year" }).key(), "year");
+});
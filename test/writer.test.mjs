@@ -0,0 +1,24 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import addon from './support.mjs';
+
+const { Index, Schema } = addon;
+
+function titleSchema() {
+  return new Schema({ title: { type: "text", flags: ["STORED"] } });
+}
+
+test('the default heap size is enough for a writer', () => {
+  const index = new Index(null, titleSchema(), {});
+  index.addDocument({ title: "Emma" });
+  index.commitSync();
+});
+
+test('writer threads can be configured with a heap size per thread', () => {
+  const index = new Index(null, titleSchema(), { numThreads: 2, heapSizePerThread: 20_000_000 });
+  index.addDocument({ title: "Emma" });
+  index.commitSync();
+  assert.throws(() => new Index(null, titleSchema(), { heapSizePerThread: 20_000_000 }), /requires 'numThreads'/);
+  assert.throws(() => new Index(null, titleSchema(), { numThreads: 0 }), RangeError);
+  assert.throws(() => new Index(null, titleSchema(), { numThreads: 4, heapSize: 20_000_000 }), /below the minimum/);
+});